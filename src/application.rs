@@ -2,10 +2,17 @@ pub use crate::utility::*;
 
 pub use crate::slide::*;
 pub use crate::application_states::*;
-
-const DEFAULT_LAST_WRITE_TIMER_INTERVAL : f32 = 0.20;
+use crate::keymap::Keymap;
+use crate::settings::Settings;
+use crate::recent_files::RecentFiles;
+use crate::file_watcher::FileWatcher;
+use crate::graphics_context::SDL2GraphicsContext;
 
 const DEFAULT_SLIDE_WHEN_NONE_GIVEN : &'static str = "./";
+// page turns come in bursts, so wait for things to settle before writing recent.conf.
+const RECENT_FILES_SAVE_DELAY : f32 = 5.0;
+// nobody goes back through more links than this, the oldest ones are forgotten.
+const MAX_PAGE_HISTORY : usize = 64;
 
 pub struct ApplicationState {
     pub state: ApplicationScreen,
@@ -13,39 +20,260 @@ pub struct ApplicationState {
     pub current_working_directory: std::path::PathBuf,
     pub currently_selected_resolution: usize,
     pub currently_selected_directory: usize,
-    pub last_write_timer: f32,
+    pub currently_selected_overview_page: usize,
+    pub typed_page_number: String,
+    // pages we followed links from, for going back.
+    pub page_history: Vec<isize>,
+    pub file_watcher: FileWatcher,
+    // None until the first page turn, that's when the talk actually starts.
+    pub presentation_elapsed_time: Option<f32>,
     pub slideshow: Option<Slide>,
+    // what was asked to be opened. Kept even if it didn't compile, so saving a fix
+    // to it still loads it.
+    pub slideshow_file_name: Option<String>,
+    // why the last reload didn't work, while we keep showing the version that did.
+    pub slideshow_error: Option<SlideCompileError>,
+    pub keymap: Keymap,
+    pub settings: Settings,
+    pub recent_files: RecentFiles,
+    pub show_debug_overlay: bool,
+    // None until the page of a newly loaded slideshow has been recorded.
+    last_remembered_page: Option<isize>,
+    // seconds since recent_files changed without being saved.
+    unsaved_recent_files_time: Option<f32>,
 }
 
 impl ApplicationState {
     pub fn new(command_line_arguments: &Vec<String>) -> ApplicationState {
-        ApplicationState {
+        let settings = Settings::load();
+        let current_working_directory =
+            settings.last_directory.clone()
+            .filter(|directory| directory.is_dir())
+            .unwrap_or_else(|| std::path::PathBuf::from("./").canonicalize().unwrap());
+
+        let mut application_state = ApplicationState {
             state: ApplicationScreen::ShowingSlide(ShowingSlideState),
-            current_working_directory: std::path::PathBuf::from("./").canonicalize().unwrap(),
+            current_working_directory,
             currently_selected_resolution: 0,
             currently_selected_directory: 0,
-            last_write_timer: 0.0,
+            currently_selected_overview_page: 0,
+            typed_page_number: String::new(),
+            page_history: Vec::new(),
+            keymap: Keymap::load(),
+            settings,
+            recent_files: RecentFiles::load(),
+            show_debug_overlay: false,
+            last_remembered_page: None,
+            unsaved_recent_files_time: None,
+            file_watcher: FileWatcher::new(),
+            presentation_elapsed_time: None,
+            slideshow: None,
+            slideshow_file_name: None,
+            slideshow_error: None,
+        };
 
-            slideshow:
-            Slide::new_from_file(
-                match command_line_arguments.len() {
-                    1 => {
-                        DEFAULT_SLIDE_WHEN_NONE_GIVEN
-                    }
-                    2 => {
-                        &command_line_arguments[1]
-                    },
-                    _ => {
-                        println!("The only command line argument should be the slide file!");
-                        DEFAULT_SLIDE_WHEN_NONE_GIVEN
+        application_state.load_slideshow(
+            match command_line_arguments.len() {
+                1 => {
+                    DEFAULT_SLIDE_WHEN_NONE_GIVEN
+                }
+                2 => {
+                    &command_line_arguments[1]
+                },
+                _ => {
+                    println!("The only command line argument should be the slide file!");
+                    DEFAULT_SLIDE_WHEN_NONE_GIVEN
+                }
+            }
+        );
+        application_state
+    }
+
+    // Everything that opens a slideshow should come through here, so the
+    // recent files list knows about it.
+    pub fn load_slideshow(&mut self, file_name: &str) {
+        match Slide::compile_from_file(file_name) {
+            Ok(slideshow) => {
+                self.slideshow = Some(slideshow);
+                self.slideshow_error = None;
+            },
+            // with no slideshow given we start in a directory, that's not an error.
+            Err(_) if std::path::Path::new(file_name).is_dir() => {
+                self.slideshow = None;
+                self.slideshow_error = None;
+            },
+            Err(error) => {
+                println!("warning: {}: {}", file_name, error);
+                self.slideshow = None;
+                self.slideshow_error = Some(error);
+            }
+        }
+        self.slideshow_file_name = Some(file_name.to_owned());
+        self.page_history.clear();
+        self.typed_page_number.clear();
+        self.presentation_elapsed_time = None;
+        self.last_remembered_page = None;
+        self.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+
+        if self.slideshow.is_some() {
+            self.recent_files.add(file_name);
+            self.save_recent_files();
+
+            if let Some(recent_file) = self.recent_files.find(file_name) {
+                if recent_file.last_page > 0 {
+                    self.state = ApplicationScreen::ResumePrompt(ResumePromptState { page: recent_file.last_page });
+                }
+            }
+        }
+
+        self.watch_slideshow_files();
+    }
+
+    pub fn reload_slideshow(&mut self) {
+        if let Some(slideshow) = &mut self.slideshow {
+            match slideshow.reload() {
+                Ok(()) => {
+                    self.slideshow_error = None;
+                },
+                Err(error) => {
+                    println!("warning: {}: {}, still showing the last version", slideshow.file_name, error);
+                    self.slideshow_error = Some(error);
+                }
+            }
+        }
+
+        // the new version might use different images or fonts.
+        self.watch_slideshow_files();
+    }
+
+    pub fn close_slideshow(&mut self) {
+        self.save_unsaved_recent_files();
+        self.slideshow = None;
+        self.slideshow_file_name = None;
+        self.slideshow_error = None;
+        self.watch_slideshow_files();
+    }
+
+    fn watch_slideshow_files(&mut self) {
+        let default_font = self.settings.default_font.as_str();
+        match (&self.slideshow, &self.slideshow_file_name) {
+            (Some(slideshow), _) => {
+                let mut files = slideshow.referenced_files();
+                files.push(default_font);
+                self.file_watcher.watch_files(files);
+            },
+            // it didn't load, but it might once it's fixed. A directory is just the
+            // browser's starting point though, there's nothing in it to wait for.
+            (None, Some(file_name)) if !std::path::Path::new(file_name).is_dir() => {
+                self.file_watcher.watch_files(vec![file_name.as_str()]);
+            },
+            _ => {
+                self.file_watcher.watch_files(Vec::new());
+            }
+        }
+    }
+
+    pub fn open_slideshow(&mut self, graphics_context: &mut SDL2GraphicsContext, file_name: &str) {
+        graphics_context.clear_resources();
+        self.load_slideshow(file_name);
+    }
+
+    // Also writes whatever the last slideshow left unsaved, since it's
+    // called when switching decks.
+    pub fn save_recent_files(&mut self) {
+        self.recent_files.save();
+        self.unsaved_recent_files_time = None;
+    }
+
+    // For closing and quitting, where there's no waiting out the delay.
+    pub fn save_unsaved_recent_files(&mut self) {
+        if self.unsaved_recent_files_time.is_some() {
+            self.save_recent_files();
+        }
+    }
+
+    fn remember_current_page(&mut self, delta_time: f32) {
+        // the prompt is still deciding which page we're on.
+        if let ApplicationScreen::ResumePrompt(_) = self.state {
+            return;
+        }
+
+        if let Some(slideshow) = &self.slideshow {
+            let current_page = slideshow.current_page();
+            if Some(current_page) != self.last_remembered_page {
+                // the first page after loading is whatever the resume prompt
+                // settled on, which is worth writing down straight away.
+                let just_loaded = self.last_remembered_page.is_none();
+                self.last_remembered_page = Some(current_page);
+
+                if self.recent_files.remember_page(&slideshow.file_name, current_page) {
+                    if just_loaded {
+                        self.save_recent_files();
+                    } else if self.unsaved_recent_files_time.is_none() {
+                        self.unsaved_recent_files_time = Some(0.0);
                     }
                 }
-            )
+            }
+        }
+
+        if let Some(unsaved_time) = &mut self.unsaved_recent_files_time {
+            *unsaved_time += delta_time;
+            if *unsaved_time >= RECENT_FILES_SAVE_DELAY {
+                self.save_recent_files();
+            }
+        }
+    }
+
+    // The window exists before the settings are read, so they get applied afterwards.
+    pub fn apply_settings(&self, graphics_context: &mut SDL2GraphicsContext) {
+        if let Some(resolution) = self.settings.window_resolution {
+            graphics_context.set_resolution(resolution);
+        }
+
+        if self.settings.fullscreen != graphics_context.is_fullscreen() {
+            graphics_context.toggle_fullscreen();
         }
+
+        graphics_context.set_text_cache_budget(self.settings.text_cache_budget as usize * 1024 * 1024);
     }
 
-    pub fn update(&mut self, delta_time: f32) {
-        self.try_and_hot_reload(delta_time);
+    pub fn toggle_fullscreen(&mut self, graphics_context: &mut SDL2GraphicsContext) {
+        graphics_context.toggle_fullscreen();
+        self.settings.fullscreen = graphics_context.is_fullscreen();
+        self.settings.save();
+    }
+
+    pub fn set_window_resolution(&mut self, graphics_context: &mut SDL2GraphicsContext, resolution: (u32, u32)) {
+        graphics_context.set_resolution(resolution);
+        self.settings.window_resolution = Some(resolution);
+        self.settings.save();
+    }
+
+    pub fn push_page_history(&mut self, page: isize) {
+        if self.page_history.len() >= MAX_PAGE_HISTORY {
+            self.page_history.remove(0);
+        }
+        self.page_history.push(page);
+    }
+
+    pub fn start_presentation_timer(&mut self) {
+        if self.presentation_elapsed_time.is_none() {
+            self.presentation_elapsed_time = Some(0.0);
+        }
+    }
+
+    pub fn remember_current_working_directory(&mut self) {
+        self.settings.last_directory = Some(self.current_working_directory.clone());
+        self.settings.save();
+    }
+
+    pub fn update(&mut self, graphics_context: &mut SDL2GraphicsContext, delta_time: f32) {
+        self.try_and_hot_reload(graphics_context, delta_time);
+
+        if let (Some(_), Some(elapsed_time)) = (&self.slideshow, &mut self.presentation_elapsed_time) {
+            *elapsed_time += delta_time;
+        }
+        self.remember_current_page(delta_time);
 
         match self.state.clone() {
             ApplicationScreen::Quit(state) => {
@@ -66,6 +294,18 @@ impl ApplicationState {
             ApplicationScreen::ChangePage(state) => {
                 state.update(self, delta_time);
             },
+            ApplicationScreen::Presenter(state) => {
+                state.update(self, delta_time);
+            },
+            ApplicationScreen::Overview(state) => {
+                state.update(self, delta_time);
+            },
+            ApplicationScreen::BlankScreen(state) => {
+                state.update(self, delta_time);
+            },
+            ApplicationScreen::ResumePrompt(state) => {
+                state.update(self, delta_time);
+            },
         }
     }
 
@@ -89,6 +329,92 @@ impl ApplicationState {
             ApplicationScreen::ShowingSlide(state) => {
                 state.draw(self, graphics_context);
             },
+            ApplicationScreen::Presenter(state) => {
+                state.draw(self, graphics_context);
+            },
+            ApplicationScreen::Overview(state) => {
+                state.draw(self, graphics_context);
+            },
+            ApplicationScreen::BlankScreen(state) => {
+                state.draw(self, graphics_context);
+            },
+            ApplicationScreen::ResumePrompt(state) => {
+                state.draw(self, graphics_context);
+            },
+        }
+
+        match self.state {
+            ApplicationScreen::ShowingSlide(_) | ApplicationScreen::ChangePage(_) |
+            ApplicationScreen::Presenter(_) | ApplicationScreen::Overview(_) |
+            ApplicationScreen::InvalidOrNoSlide(_) => {
+                self.draw_slideshow_error(graphics_context);
+            },
+            _ => {}
+        }
+
+        if self.show_debug_overlay {
+            self.draw_debug_overlay(graphics_context);
+        }
+    }
+
+    // Not cached itself, the numbers change every frame and would just push slide text out.
+    fn draw_debug_overlay(&self, graphics_context: &mut SDL2GraphicsContext) {
+        let default_font = graphics_context.add_font(&self.settings.default_font);
+        graphics_context.camera = Camera::default();
+        graphics_context.use_viewport_default();
+        graphics_context.logical_resolution = VirtualResolution::Display;
+
+        const MEGABYTE : f32 = 1024.0 * 1024.0;
+        let stats = graphics_context.text_cache_stats();
+        let message = format!("text cache: {} textures, {:.1}/{:.0} MB, {} hits, {} misses, {} evictions",
+                              stats.textures,
+                              stats.bytes_used as f32 / MEGABYTE,
+                              stats.budget_bytes as f32 / MEGABYTE,
+                              stats.hits, stats.misses, stats.evictions);
+
+        let font_size = graphics_context.font_size_percent(0.025);
+        let (width, height) = graphics_context.text_dimensions(default_font, &message, font_size);
+        let (width, height) = (width as f32 + font_size as f32, height as f32);
+        let x = font_size as f32 / 2.0;
+        let y = font_size as f32 / 2.0;
+
+        graphics_context.render_filled_rectangle(x, y, width, height, Color::new(10, 10, 16, 220));
+        graphics_context.render_text(default_font,
+                                     x + font_size as f32 / 2.0, y,
+                                     &message,
+                                     font_size,
+                                     COLOR_WHITE,
+                                     sdl2::ttf::FontStyle::NORMAL);
+    }
+
+    // Small and in the corner, since the audience can probably see it too. Also
+    // says why a slideshow didn't open at all.
+    fn draw_slideshow_error(&self, graphics_context: &mut SDL2GraphicsContext) {
+        if let (Some(slideshow_file_name), Some(error)) = (&self.slideshow_file_name, &self.slideshow_error) {
+            let default_font = graphics_context.add_font(&self.settings.default_font);
+            graphics_context.camera = Camera::default();
+            graphics_context.use_viewport_default();
+            graphics_context.logical_resolution = VirtualResolution::Display;
+
+            let file_name = std::path::Path::new(slideshow_file_name)
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| slideshow_file_name.clone());
+            let message = format!("{}, {}", file_name, error);
+
+            let font_size = graphics_context.font_size_percent(0.03);
+            let (width, height) = graphics_context.text_dimensions(default_font, &message, font_size);
+            let (width, height) = (width as f32 + font_size as f32, height as f32);
+            let x = font_size as f32;
+            let y = graphics_context.logical_height() as f32 - height - font_size as f32;
+
+            graphics_context.render_filled_rectangle(x, y, width, height, Color::new(10, 10, 16, 220));
+            graphics_context.render_text(default_font,
+                                         x + font_size as f32 / 2.0, y,
+                                         &message,
+                                         font_size,
+                                         COLOR_ERROR_RED,
+                                         sdl2::ttf::FontStyle::NORMAL);
         }
     }
 
@@ -112,17 +438,41 @@ impl ApplicationState {
             ApplicationScreen::ShowingSlide(state) => {
                 state.handle_event(self, graphics_context, event_pump, delta_time);
             },
+            ApplicationScreen::Presenter(state) => {
+                state.handle_event(self, graphics_context, event_pump, delta_time);
+            },
+            ApplicationScreen::Overview(state) => {
+                state.handle_event(self, graphics_context, event_pump, delta_time);
+            },
+            ApplicationScreen::BlankScreen(state) => {
+                state.handle_event(self, graphics_context, event_pump, delta_time);
+            },
+            ApplicationScreen::ResumePrompt(state) => {
+                state.handle_event(self, graphics_context, event_pump, delta_time);
+            },
         }
     }
 
-    fn try_and_hot_reload(&mut self, delta_time: f32) {
-        if let Some(slideshow) = &mut self.slideshow {
-            if self.last_write_timer <= 0.0 {
-                self.last_write_timer = DEFAULT_LAST_WRITE_TIMER_INTERVAL;
-                slideshow.reload().expect("should be successful.");
-            }
+    // Only the caches for files that changed are thrown away, everything else
+    // the slideshow uses stays loaded.
+    fn try_and_hot_reload(&mut self, graphics_context: &mut SDL2GraphicsContext, delta_time: f32) {
+        let changed_files = self.file_watcher.poll(delta_time, self.settings.hot_reload_interval);
+        if changed_files.is_empty() {
+            return;
         }
 
-        self.last_write_timer -= delta_time;
+        let mut slideshow_changed = false;
+        for file_name in &changed_files {
+            graphics_context.invalidate_file(file_name);
+            slideshow_changed |= self.slideshow_file_name.as_ref() == Some(file_name);
+        }
+
+        if slideshow_changed {
+            match (&self.slideshow, self.slideshow_file_name.clone()) {
+                (Some(_), _) => self.reload_slideshow(),
+                (None, Some(file_name)) => self.load_slideshow(&file_name),
+                (None, None) => {},
+            }
+        }
     }
 }
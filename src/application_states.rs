@@ -6,6 +6,7 @@ pub use crate::slide::*;
 pub use crate::color::*;
 pub use crate::graphics_context::*;
 pub use crate::application::*;
+pub use crate::keymap::*;
 
 pub trait ApplicationScreenState {
     fn handle_event(&self,
@@ -36,7 +37,17 @@ pub struct ShowingSlideState;
 #[derive(Clone)]
 pub struct SelectSlideToLoadState;
 #[derive(Clone)]
-pub struct ChangePageState {pub from: isize, pub to: isize,}
+pub struct PresenterState;
+#[derive(Clone)]
+pub struct OverviewState;
+#[derive(Clone)]
+pub struct BlankScreenState {pub color: Color,}
+#[derive(Clone)]
+pub struct ResumePromptState {pub page: isize,}
+#[derive(Clone)]
+// transition_page is whose transition gets played. Normally that's the page we're
+// leaving, but jumps use the page we're going to.
+pub struct ChangePageState {pub from: isize, pub to: isize, pub transition_page: isize,}
 #[derive(Clone)]
 pub struct QuitState;
 #[derive(Clone)]
@@ -46,6 +57,10 @@ pub enum ApplicationScreen {
     ShowingSlide(ShowingSlideState),
     SelectSlideToLoad(SelectSlideToLoadState),
     ChangePage(ChangePageState),
+    Presenter(PresenterState),
+    Overview(OverviewState),
+    BlankScreen(BlankScreenState),
+    ResumePrompt(ResumePromptState),
     Quit(QuitState),
 }
 
@@ -0,0 +1,28 @@
+pub use crate::application_states::*;
+
+// Nothing changes underneath, so coming back resumes on the same page.
+impl ApplicationScreenState for BlankScreenState {
+    fn handle_event(&self,
+                    app: &mut ApplicationState,
+                    _graphics_context: &mut SDL2GraphicsContext,
+                    event_pump: &mut sdl2::EventPump,
+                    _delta_time: f32) {
+        for event in event_pump.poll_iter() {
+            match event {
+                SDLEvent::Quit {..} => {
+                    app.state = ApplicationScreen::Quit(QuitState);
+                },
+                SDLEvent::KeyDown {..} | SDLEvent::MouseButtonDown {..} => {
+                    app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&self,
+            _app: &ApplicationState,
+            graphics_context: &mut SDL2GraphicsContext) {
+        graphics_context.clear_color(self.color);
+    }
+}
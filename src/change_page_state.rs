@@ -14,7 +14,7 @@ impl ApplicationScreenState for ChangePageState {
                 SDLEvent::KeyDown {..} => {
                     app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
                     if let Some(slideshow) = &mut app.slideshow {
-                        slideshow.finish_transition();
+                        slideshow.finish_transition(self.transition_page as usize);
                     }
                 },
                 _ => {}
@@ -25,14 +25,14 @@ impl ApplicationScreenState for ChangePageState {
     fn draw(&self,
             app: &ApplicationState,
             graphics_context: &mut SDL2GraphicsContext) {
-        let default_font = graphics_context.add_font("data/fonts/libre-baskerville/LibreBaskerville-Regular.ttf");
+        let default_font = graphics_context.add_font(&app.settings.default_font);
         let first = self.from;
         let second = self.to;
         #[cfg(debug_assertions)]
         graphics_context.clear_color(Color::new(255, 0, 0, 255));
         let slideshow = &app.slideshow.as_ref().unwrap();
 
-        let page_with_transition = &slideshow.get(self.from as usize);
+        let page_with_transition = &slideshow.get(self.transition_page as usize);
         if let Some(transition) = &page_with_transition.unwrap().transition {
             let easing_amount = transition.easing_amount();
             let forward_direction = second > first;
@@ -100,9 +100,9 @@ impl ApplicationScreenState for ChangePageState {
         if let Some(slideshow) = &mut app.slideshow {
             let valid_transition = slideshow.get(first as usize).is_some() && slideshow.get(second as usize).is_some();
 
-            if let None = slideshow.get_mut(self.from as usize).unwrap().transition {
+            if slideshow.get(self.transition_page as usize).unwrap().transition.is_none() {
                 app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
-            } else if let Some(transition) = &mut slideshow.get_mut(self.from as usize).unwrap().transition {
+            } else if let Some(transition) = &mut slideshow.get_mut(self.transition_page as usize).unwrap().transition {
                 if valid_transition && !transition.finished_transition() {
                     transition.time += delta_time;
                 } else {
@@ -0,0 +1,164 @@
+/*
+    Syntax highlighting for $code blocks.
+
+    This is syntect with the grammars and themes it comes with (they're Sublime Text's),
+    so Rust, C, Python, shell and JSON all work, and so does anything else in that set,
+    by name or by file extension.
+
+    Loading the grammars takes a moment, so it's only done the first time a deck has
+    code in it. Highlighting happens when the slide is compiled, drawing is just colored
+    text after that.
+*/
+use syntect::highlighting::{FontStyle, HighlightIterator, HighlightState, Highlighter, Theme, ThemeSet};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxSet};
+
+use crate::color::Color;
+
+// Inline `code` too.
+pub const CODE_FONT : &'static str = "data/fonts/Inconsolata/Inconsolata-Regular.ttf";
+
+// Picked by how bright the page is, unless there's a $code-theme.
+pub const DEFAULT_DARK_CODE_THEME : &'static str = "base16-ocean.dark";
+pub const DEFAULT_LIGHT_CODE_THEME : &'static str = "InspiredGitHub";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeSpan {
+    pub text: String,
+    pub color: Color,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CodeTheme {
+    pub background: Color,
+    pub line_number: Color,
+}
+
+pub struct CodeHighlighter {
+    syntaxes: SyntaxSet,
+    themes: ThemeSet,
+}
+
+fn to_color(color: syntect::highlighting::Color) -> Color {
+    Color::new(color.r, color.g, color.b, color.a)
+}
+
+// Names people write after $code: that aren't what the grammar calls itself.
+fn language_token(language: &str) -> &str {
+    match language {
+        "shell" | "bash" | "zsh" => "sh",
+        "c++" => "cpp",
+        _ => language,
+    }
+}
+
+pub fn default_code_theme(background: Color) -> &'static str {
+    let brightness = 0.299 * background.r as f32 + 0.587 * background.g as f32 + 0.114 * background.b as f32;
+    if brightness < 128.0 {
+        DEFAULT_DARK_CODE_THEME
+    } else {
+        DEFAULT_LIGHT_CODE_THEME
+    }
+}
+
+impl CodeHighlighter {
+    pub fn get() -> &'static CodeHighlighter {
+        static CODE_HIGHLIGHTER : std::sync::OnceLock<CodeHighlighter> = std::sync::OnceLock::new();
+        CODE_HIGHLIGHTER.get_or_init(
+            || {
+                CodeHighlighter {
+                    syntaxes: SyntaxSet::load_defaults_newlines(),
+                    themes: ThemeSet::load_defaults(),
+                }
+            })
+    }
+
+    pub fn has_language(&self, language: &str) -> bool {
+        language.is_empty() || self.syntaxes.find_syntax_by_token(language_token(language)).is_some()
+    }
+
+    pub fn has_theme(&self, theme_name: &str) -> bool {
+        self.themes.themes.contains_key(theme_name)
+    }
+
+    fn find_theme(&self, theme_name: &str) -> Result<&Theme, &'static str> {
+        self.themes.themes.get(theme_name).ok_or("No code theme by that name.")
+    }
+
+    pub fn theme(&self, theme_name: &str) -> Result<CodeTheme, &'static str> {
+        let settings = &self.find_theme(theme_name)?.settings;
+        let background = settings.background.map(to_color).unwrap_or(Color::new(40, 44, 52, 255));
+        let foreground = settings.foreground.map(to_color).unwrap_or(Color::new(220, 220, 220, 255));
+        let line_number = settings.gutter_foreground.map(to_color)
+            .unwrap_or(Color { a: foreground.a / 2, .. foreground });
+        Ok(CodeTheme { background, line_number })
+    }
+
+    // One list of spans per line. An empty language is plain text, in the theme's colors.
+    pub fn highlight(&self, language: &str, lines: &[String], theme_name: &str) -> Result<Vec<Vec<CodeSpan>>, &'static str> {
+        let syntax =
+            if language.is_empty() {
+                self.syntaxes.find_syntax_plain_text()
+            } else {
+                self.syntaxes.find_syntax_by_token(language_token(language))
+                    .ok_or("No syntax highlighting for that language.")?
+            };
+        let highlighter = Highlighter::new(self.find_theme(theme_name)?);
+
+        let mut parse_state = ParseState::new(syntax);
+        let mut highlight_state = HighlightState::new(&highlighter, ScopeStack::new());
+        let mut highlighted_lines = Vec::with_capacity(lines.len());
+
+        for line in lines {
+            // the grammars want to see where lines end.
+            let line = format!("{}\n", line);
+            let operations = parse_state.parse_line(&line, &self.syntaxes)
+                .map_err(|_| "Could not highlight this code.")?;
+
+            let spans : Vec<CodeSpan> =
+                HighlightIterator::new(&mut highlight_state, &operations, &line, &highlighter)
+                .map(|(style, text)| {
+                    CodeSpan {
+                        text: text.trim_end_matches('\n').to_owned(),
+                        color: to_color(style.foreground),
+                        bold: style.font_style.contains(FontStyle::BOLD),
+                        italic: style.font_style.contains(FontStyle::ITALIC),
+                    }
+                })
+                .filter(|span| !span.text.is_empty())
+                .collect();
+            highlighted_lines.push(spans);
+        }
+
+        Ok(highlighted_lines)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn highlights_the_languages_we_use() {
+    let highlighter = CodeHighlighter::get();
+    for language in &["rust", "c", "python", "shell", "sh", "json"] {
+        assert!(highlighter.has_language(language), "{}", language);
+    }
+    assert!(!highlighter.has_language("not a language"));
+    assert!(highlighter.highlight("not a language", &[], DEFAULT_DARK_CODE_THEME).is_err());
+    assert!(highlighter.theme("not a theme").is_err());
+}
+#[test]
+fn keywords_and_names_are_different_colors() {
+    let highlighter = CodeHighlighter::get();
+    let lines = vec![String::from("fn main() {"), String::from("    let x = 1;"), String::from("}")];
+    let highlighted = highlighter.highlight("rust", &lines, DEFAULT_DARK_CODE_THEME).unwrap();
+    assert_eq!(3, highlighted.len());
+
+    // nothing is lost, the spaces at the start included.
+    for (line, spans) in lines.iter().zip(&highlighted) {
+        assert_eq!(*line, spans.iter().map(|span| span.text.as_str()).collect::<String>());
+    }
+
+    let color_of = |text: &str| highlighted[1].iter().find(|span| span.text.trim() == text).map(|span| span.color);
+    assert!(color_of("let").is_some());
+    assert_ne!(color_of("let"), color_of("x"));
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -11,13 +11,9 @@ pub fn hexadecimal_to_decimal(literal: &str) -> u8 {
     for (index, ch) in literal.chars().enumerate() {
         let index = (literal.len()-1) - index;
         result +=
-            match ch {
-                '0' => {0}, '1' => {1}, '2' => {2},
-                '3' => {3}, '4' => {4}, '5' => {5},
-                '6' => {6}, '7' => {7}, '8' => {8},
-                '9' => {9}, 'A' => {10}, 'B' => {11},
-                'C' => {12}, 'D' => {13}, 'E' => {14}, 'F' => {15},
-                _ => { panic!("undefined character for hexadecimal literal!"); }
+            match ch.to_digit(16) {
+                Some(digit) => { digit as u8 },
+                None => { panic!("undefined character for hexadecimal literal!"); }
             } * 16_u8.pow(index as u32);
     }
     result
@@ -33,7 +29,17 @@ impl Color {
         // 255... This needs to be templated.
         if let Some('#') = hex.chars().nth(0) {
             let hex = &hex[1..];
+            if !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+                println!("Error parsing hexadecimal literal");
+                return None;
+            }
             match hex.len() {
+                // F00, short for FF0000
+                // F00F
+                3 | 4 => {
+                    let doubled : String = hex.chars().flat_map(|ch| [ch, ch]).collect();
+                    Color::parse_hexadecimal_literal(&format!("#{}", doubled))
+                },
                 // FFFFFF
                 // FFFFFFFF
                 6 | 8 => {
@@ -73,4 +79,6 @@ impl std::convert::TryFrom<&str> for Color {
 
 pub const COLOR_WHITE : Color = Color {r: 255, g: 255, b: 255, a: 255};
 pub const COLOR_BLACK : Color = Color {r: 0, g: 0, b: 0, a: 255};
+pub const COLOR_LINK_BLUE : Color = Color {r: 30, g: 100, b: 220, a: 255};
 pub const COLOR_RIPE_LEMON : Color = Color {r: 247, g: 202, b: 24, a: 255};
+pub const COLOR_ERROR_RED : Color = Color {r: 230, g: 70, b: 60, a: 255};
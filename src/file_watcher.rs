@@ -0,0 +1,269 @@
+/*
+    Watches the slideshow and everything it pulls in (images, fonts), so we only
+    reload when something actually changed instead of recompiling on a timer.
+
+    On Linux this is inotify on the directories the files live in. Watching the
+    directories instead of the files is on purpose: most editors save by writing a
+    new file and renaming it over the old one, and a watch on the old file never
+    hears about that.
+
+    Everywhere else, or if inotify isn't available, we fall back to checking
+    modification times every so often.
+
+    Editors also like to write a file in a few goes, so changes are held until
+    things have been quiet for a little while.
+*/
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::utility::file_last_modified_time;
+
+const POLLING_INTERVAL : f32 = 0.5;
+
+struct WatchedFile {
+    // the name the rest of the program knows it by, since caches are keyed on it.
+    file_name: String,
+    last_modified_time: Option<SystemTime>,
+}
+
+pub struct FileWatcher {
+    watched_files: HashMap<PathBuf, WatchedFile>,
+    changed_files: HashSet<PathBuf>,
+    quiet_time: f32,
+    polling_timer: f32,
+
+    #[cfg(target_os = "linux")]
+    inotify: Option<Inotify>,
+}
+
+// Events come back as directory + name, so this is the form everything is compared in.
+fn watched_path(file_name: &str) -> PathBuf {
+    let path = Path::new(file_name);
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    let directory = std::fs::canonicalize(directory).unwrap_or_else(|_| directory.to_path_buf());
+
+    match path.file_name() {
+        Some(name) => directory.join(name),
+        None => path.to_path_buf(),
+    }
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher {
+            watched_files: HashMap::new(),
+            changed_files: HashSet::new(),
+            quiet_time: 0.0,
+            polling_timer: 0.0,
+
+            #[cfg(target_os = "linux")]
+            inotify: Inotify::new(),
+        }
+    }
+
+    // Replaces whatever was watched before.
+    pub fn watch_files<'a, I: IntoIterator<Item = &'a str>>(&mut self, file_names: I) {
+        self.watched_files.clear();
+        self.changed_files.clear();
+
+        for file_name in file_names {
+            let path = watched_path(file_name);
+            let last_modified_time = file_last_modified_time(&path);
+            self.watched_files.insert(path, WatchedFile { file_name: file_name.to_owned(), last_modified_time });
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(inotify) = &mut self.inotify {
+                let directories : HashSet<&Path> = self.watched_files.keys().filter_map(|path| path.parent()).collect();
+                inotify.watch_directories(&directories);
+            }
+        }
+    }
+
+    // Returns the files that changed, once they've stopped changing for debounce_time seconds.
+    pub fn poll(&mut self, delta_time: f32, debounce_time: f32) -> Vec<String> {
+        let changes = self.read_changes(delta_time);
+        let mut heard_something = false;
+        for path in changes {
+            if self.watched_files.contains_key(&path) {
+                self.changed_files.insert(path);
+                heard_something = true;
+            }
+        }
+
+        if heard_something {
+            self.quiet_time = 0.0;
+            return Vec::new();
+        }
+
+        self.quiet_time += delta_time;
+        if self.changed_files.is_empty() || self.quiet_time < debounce_time {
+            return Vec::new();
+        }
+
+        let watched_files = &self.watched_files;
+        self.changed_files.drain()
+            .filter_map(|path| watched_files.get(&path).map(|watched_file| watched_file.file_name.clone()))
+            .collect()
+    }
+
+    fn read_changes(&mut self, delta_time: f32) -> Vec<PathBuf> {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(inotify) = &mut self.inotify {
+                return inotify.read_changes();
+            }
+        }
+
+        self.poll_modified_times(delta_time)
+    }
+
+    fn poll_modified_times(&mut self, delta_time: f32) -> Vec<PathBuf> {
+        let mut changes = Vec::new();
+
+        self.polling_timer -= delta_time;
+        if self.polling_timer <= 0.0 {
+            self.polling_timer = POLLING_INTERVAL;
+
+            for (path, watched_file) in self.watched_files.iter_mut() {
+                let last_modified_time = file_last_modified_time(path);
+                if last_modified_time != watched_file.last_modified_time {
+                    watched_file.last_modified_time = last_modified_time;
+                    changes.push(path.clone());
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct Inotify {
+    file_descriptor: i32,
+    watched_directories: HashMap<i32, PathBuf>,
+}
+
+#[cfg(target_os = "linux")]
+impl Inotify {
+    fn new() -> Option<Inotify> {
+        let file_descriptor = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if file_descriptor < 0 {
+            println!("warning: inotify isn't available, checking files for changes every {} seconds", POLLING_INTERVAL);
+            None
+        } else {
+            Some(Inotify { file_descriptor, watched_directories: HashMap::new() })
+        }
+    }
+
+    // Directories that are still wanted keep their watch, so nothing is missed
+    // while the slideshow is being reloaded.
+    fn watch_directories(&mut self, directories: &HashSet<&Path>) {
+        use std::os::unix::ffi::OsStrExt;
+
+        let file_descriptor = self.file_descriptor;
+        self.watched_directories.retain(
+            |watch_descriptor, directory| {
+                let still_wanted = directories.contains(directory.as_path());
+                if !still_wanted {
+                    unsafe { libc::inotify_rm_watch(file_descriptor, *watch_descriptor); }
+                }
+                still_wanted
+            });
+
+        for directory in directories {
+            if self.watched_directories.values().any(|watched_directory| watched_directory == directory) {
+                continue;
+            }
+
+            let watch_descriptor = match std::ffi::CString::new(directory.as_os_str().as_bytes()) {
+                Ok(directory_c_string) => unsafe {
+                    libc::inotify_add_watch(file_descriptor,
+                                            directory_c_string.as_ptr(),
+                                            libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_DELETE)
+                },
+                Err(_) => -1,
+            };
+
+            if watch_descriptor < 0 {
+                println!("warning: can't watch {} for changes", directory.display());
+            } else {
+                self.watched_directories.insert(watch_descriptor, directory.to_path_buf());
+            }
+        }
+    }
+
+    // Full paths of everything touched in the watched directories since last time.
+    fn read_changes(&mut self) -> Vec<PathBuf> {
+        use std::os::unix::ffi::OsStrExt;
+        const EVENT_HEADER_SIZE : usize = std::mem::size_of::<libc::inotify_event>();
+
+        let mut changes = Vec::new();
+        let mut buffer = [0u8; 4096];
+
+        loop {
+            let length = unsafe {
+                libc::read(self.file_descriptor, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+            };
+            // nonblocking, so this is also how we find out there's nothing left.
+            if length <= 0 {
+                break;
+            }
+
+            let length = length as usize;
+            let mut offset = 0;
+            while offset + EVENT_HEADER_SIZE <= length {
+                let event : libc::inotify_event = unsafe {
+                    std::ptr::read_unaligned(buffer.as_ptr().add(offset) as *const libc::inotify_event)
+                };
+                let name_start = offset + EVENT_HEADER_SIZE;
+                let name_end = (name_start + event.len as usize).min(length);
+                // the name is padded out with nuls.
+                let name = buffer[name_start..name_end].split(|byte| *byte == 0).next().unwrap_or(&[]);
+
+                if let Some(directory) = self.watched_directories.get(&event.wd) {
+                    if !name.is_empty() {
+                        changes.push(directory.join(std::ffi::OsStr::from_bytes(name)));
+                    }
+                }
+
+                offset = name_end;
+            }
+        }
+
+        changes
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.file_descriptor); }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn file_watcher_waits_until_quiet() {
+    let path = std::env::temp_dir().join(format!("slideshow-watch-test-{}.slide", std::process::id()));
+    let file_name = path.to_str().unwrap().to_owned();
+    std::fs::write(&path, "$page\nbefore\n").unwrap();
+
+    let mut file_watcher = FileWatcher::new();
+    file_watcher.watch_files(vec![file_name.as_str()]);
+    assert!(file_watcher.poll(1.0, 0.1).is_empty());
+
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    std::fs::write(&path, "$page\nafter\n").unwrap();
+    // heard about it, but it might still be getting written.
+    assert!(file_watcher.poll(0.0, 0.1).is_empty());
+    let changed_files : Vec<String> = (0..3).flat_map(|_| file_watcher.poll(1.0, 0.1)).collect();
+    assert_eq!(vec![file_name], changed_files);
+
+    std::fs::remove_file(&path).unwrap();
+}
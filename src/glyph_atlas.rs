@@ -0,0 +1,195 @@
+/*
+    The other way to draw text: shape it, then draw each glyph out of one big texture.
+
+    The line cache (render_static_text) is still what slides use, since a slide's text
+    doesn't change and a whole line is one copy. But anything that changes every frame,
+    like the presenter timer or page counters, would make a brand new line texture every
+    time it ticks. Here the glyphs are rasterized once and reused by every string.
+
+    Strings are shaped first (see text_shaping.rs) so ligatures, kerning and right to
+    left text come out the same as they would as a line. Rasterizing is ab_glyph.
+
+    Glyphs are packed into rows ("shelves") of one texture. When it fills up it's just
+    emptied and packing starts over, everything still in use gets drawn back in quickly.
+*/
+use std::collections::HashMap;
+
+use crate::Color;
+use crate::text_shaping::ShapedGlyph;
+
+const ATLAS_SIZE : u32 = 1024;
+// so glyphs never bleed into their neighbours.
+const GLYPH_PADDING : u32 = 1;
+
+#[derive(Clone, Copy)]
+struct AtlasGlyph {
+    // zero sized for glyphs with nothing to draw, like spaces.
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    // from the pen on the baseline to the top left of the bitmap.
+    left: i32,
+    top: i32,
+}
+
+// One shaped string and where to draw it. Everything here is in real pixels,
+// y is the baseline.
+pub struct GlyphRun<'a> {
+    pub glyphs: &'a [ShapedGlyph],
+    pub x: f32,
+    pub y: f32,
+    pub font_size: u16,
+    pub color: Color,
+}
+
+pub struct GlyphAtlas {
+    texture: Option<sdl2::render::Texture>,
+    // font -> (glyph id, size) -> where it is in the texture.
+    glyphs: HashMap<String, HashMap<(u16, u16), AtlasGlyph>>,
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+}
+
+impl GlyphAtlas {
+    pub fn new() -> GlyphAtlas {
+        GlyphAtlas {
+            texture: None,
+            glyphs: HashMap::new(),
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    // The texture stays, everything in it is forgotten.
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.shelf_x = 0;
+        self.shelf_y = 0;
+        self.shelf_height = 0;
+    }
+
+    pub fn remove_font(&mut self, font_id: &str) {
+        self.glyphs.remove(font_id);
+    }
+
+    fn find_space(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        let (w, h) = (w + GLYPH_PADDING, h + GLYPH_PADDING);
+        if w > ATLAS_SIZE || h > ATLAS_SIZE {
+            return None;
+        }
+
+        if self.shelf_x + w > ATLAS_SIZE {
+            self.shelf_x = 0;
+            self.shelf_y += self.shelf_height;
+            self.shelf_height = 0;
+        }
+        if self.shelf_y + h > ATLAS_SIZE {
+            return None;
+        }
+
+        let position = (self.shelf_x, self.shelf_y);
+        self.shelf_x += w;
+        self.shelf_height = self.shelf_height.max(h);
+        Some(position)
+    }
+
+    fn rasterize_glyph(&mut self,
+                       font: &ab_glyph::FontRef,
+                       glyph_id: u16,
+                       font_size: u16) -> Option<AtlasGlyph> {
+        use ab_glyph::Font;
+
+        let units_per_em = font.units_per_em()?;
+        let scale = ab_glyph::PxScale::from(font_size as f32 * font.height_unscaled() / units_per_em);
+        let glyph = ab_glyph::GlyphId(glyph_id).with_scale(scale);
+
+        let outline = match font.outline_glyph(glyph) {
+            Some(outline) => outline,
+            None => return Some(AtlasGlyph { x: 0, y: 0, w: 0, h: 0, left: 0, top: 0 }),
+        };
+        let bounds = outline.px_bounds();
+        let (w, h) = (bounds.width() as u32, bounds.height() as u32);
+
+        let (x, y) = match self.find_space(w, h) {
+            Some(position) => position,
+            None => {
+                // full, start over. Glyphs already drawn this frame were copied out already.
+                self.clear();
+                self.find_space(w, h)?
+            }
+        };
+
+        // white, so color mod can make it whatever color it's drawn in.
+        let mut pixels = vec![255u8; (w * h * 4) as usize];
+        for alpha in pixels.iter_mut().skip(3).step_by(4) {
+            *alpha = 0;
+        }
+        outline.draw(
+            |pixel_x, pixel_y, coverage| {
+                if pixel_x < w && pixel_y < h {
+                    pixels[((pixel_y * w + pixel_x) * 4 + 3) as usize] = (coverage.min(1.0) * 255.0) as u8;
+                }
+            });
+
+        if w > 0 && h > 0 {
+            self.texture.as_mut()?
+                .update(Some(sdl2::rect::Rect::new(x as i32, y as i32, w, h)), &pixels, (w * 4) as usize)
+                .ok()?;
+        }
+
+        Some(AtlasGlyph { x, y, w, h, left: bounds.min.x as i32, top: bounds.min.y as i32 })
+    }
+
+    pub fn render_glyphs(&mut self,
+                         window_canvas: &mut sdl2::render::WindowCanvas,
+                         font: &ab_glyph::FontRef,
+                         font_id: &str,
+                         run: GlyphRun) {
+        let GlyphRun { glyphs, x, y, font_size, color } = run;
+        if self.texture.is_none() {
+            let texture = window_canvas.texture_creator()
+                .create_texture_static(sdl2::pixels::PixelFormatEnum::RGBA32, ATLAS_SIZE, ATLAS_SIZE);
+            match texture {
+                Ok(mut texture) => {
+                    texture.set_blend_mode(sdl2::render::BlendMode::Blend);
+                    self.texture = Some(texture);
+                },
+                Err(_) => return,
+            }
+        }
+
+        if let Some(texture) = self.texture.as_mut() {
+            texture.set_color_mod(color.r, color.g, color.b);
+            texture.set_alpha_mod(color.a);
+        }
+
+        for glyph in glyphs {
+            let cached = self.glyphs.get(font_id).and_then(|atlas_glyphs| atlas_glyphs.get(&(glyph.glyph_id, font_size))).copied();
+            let atlas_glyph = match cached {
+                Some(atlas_glyph) => Some(atlas_glyph),
+                None => {
+                    let atlas_glyph = self.rasterize_glyph(font, glyph.glyph_id, font_size);
+                    if let Some(atlas_glyph) = atlas_glyph {
+                        self.glyphs.entry(font_id.to_owned()).or_default().insert((glyph.glyph_id, font_size), atlas_glyph);
+                    }
+                    atlas_glyph
+                }
+            };
+
+            if let (Some(atlas_glyph), Some(texture)) = (atlas_glyph, self.texture.as_mut()) {
+                if atlas_glyph.w > 0 && atlas_glyph.h > 0 {
+                    let glyph_x = (x + glyph.x).round() as i32 + atlas_glyph.left;
+                    let glyph_y = (y - glyph.y).round() as i32 + atlas_glyph.top;
+                    let _ = window_canvas.copy(texture,
+                                               Some(sdl2::rect::Rect::new(atlas_glyph.x as i32, atlas_glyph.y as i32,
+                                                                          atlas_glyph.w, atlas_glyph.h)),
+                                               Some(sdl2::rect::Rect::new(glyph_x, glyph_y,
+                                                                          atlas_glyph.w, atlas_glyph.h)));
+                }
+            }
+        }
+    }
+}
@@ -2,9 +2,7 @@
    For now since I don't want to use a texture atlas to avoid too much work,
    draw_static_text will be a thing.
 
-   It will be the same thing as draw_text, but it will save it to a cache with a hashmap.
-
-    TODO: Clear text cache.
+   It will be the same thing as draw_text, but it will save it to a cache. (see text_texture_cache.rs)
 
     NOTE(jerry): 8/3/2021
     I'm going to disable DPI scaling as this program works with a fixed defined resolution
@@ -16,51 +14,149 @@ use sdl2::pixels::Color as SDLColor;
 use std::collections::HashMap;
 
 use crate::Color;
+use crate::text_texture_cache::*;
+use crate::glyph_atlas::{GlyphAtlas, GlyphRun};
+use crate::text_shaping::*;
+use crate::shapes::*;
 type SDL2WindowCanvas = sdl2::render::Canvas<sdl2::video::Window>;
 type SDL2WindowContextTextureCreator = sdl2::render::TextureCreator<sdl2::video::WindowContext>;
 // hashmaps of hashmaps?
 // Yeah this is not a good idea, but whatever for now.
 
+// Sizes are opened when something is drawn at them. Resizing the window asks
+// for a whole new set, so only the most recently used few are kept open.
+const MAX_OPEN_SIZES_PER_FONT : usize = 12;
+
+// The same file parsed for shaping and rasterizing. Parsing isn't free, so it's
+// done once per font instead of once per string.
+struct ParsedFont {
+    face: rustybuzz::Face<'static>,
+    font: ab_glyph::FontRef<'static>,
+}
+
 // TODO: convert this to &str.
 pub struct SDL2FontAsset<'ttf> {
     file_name: String,
+    // These read from file_bytes, so they have to be declared (and dropped) before it.
     stored_sizes : HashMap<u16, sdl2::ttf::Font<'ttf, 'static>>,
+    parsed : Option<ParsedFont>,
+    // least recently used first.
+    size_use_order : Vec<u16>,
+    // the whole font file, read once and shared by every size.
+    file_bytes : Option<Box<[u8]>>,
+    // couldn't be loaded, so text in it just doesn't draw instead of taking the show down.
+    broken: bool,
 }
 
 impl<'ttf> SDL2FontAsset<'ttf> {
     fn new(file_name: String) -> SDL2FontAsset<'ttf> {
-        SDL2FontAsset {file_name: file_name.clone(), stored_sizes: HashMap::new(),}
+        SDL2FontAsset {
+            file_name,
+            stored_sizes: HashMap::new(),
+            size_use_order: Vec::new(),
+            parsed: None,
+            file_bytes: None,
+            broken: false,
+        }
     }
 
-    fn new_with_common_sizes(file_name: String, ttf_context: &'ttf sdl2::ttf::Sdl2TtfContext) -> SDL2FontAsset<'ttf> {
-        SDL2FontAsset {
-            file_name: file_name.clone(),
-            stored_sizes: {
-                let mut okay_table_i_guess = HashMap::new();
-                // probably common sizes.
-                for font_size in &[10, 11, 12, 14, 16, 18, 24, 32, 36, 48, 64, 72, 84, 96, 128] {
-                    okay_table_i_guess.insert(*font_size, ttf_context.load_font(file_name.clone(), *font_size).unwrap());
-                }
+    fn mark_size_used(&mut self, font_size: u16) {
+        if let Some(index) = self.size_use_order.iter().position(|size| *size == font_size) {
+            self.size_use_order.remove(index);
+        }
+        self.size_use_order.push(font_size);
+    }
 
-                okay_table_i_guess
+    fn load_size_if_not_loaded(&mut self, font_size: u16, ttf_context: &'ttf sdl2::ttf::Sdl2TtfContext) {
+        if self.broken {
+            return;
+        }
+        if self.stored_sizes.contains_key(&font_size) {
+            self.mark_size_used(font_size);
+            return;
+        }
+
+        let file_bytes = match self.static_file_bytes() {
+            Some(file_bytes) => file_bytes,
+            None => return,
+        };
+
+        let font = sdl2::rwops::RWops::from_bytes(file_bytes)
+            .and_then(|rwops| ttf_context.load_font_from_rwops(rwops, font_size));
+        match font {
+            Ok(font) => {
+                self.stored_sizes.insert(font_size, font);
+                self.mark_size_used(font_size);
+            },
+            Err(error) => {
+                println!("warning: couldn't load font \"{}\": {}", self.file_name, error);
+                self.broken = true;
+                return;
             }
         }
+
+        while self.size_use_order.len() > MAX_OPEN_SIZES_PER_FONT {
+            let least_recently_used = self.size_use_order.remove(0);
+            self.stored_sizes.remove(&least_recently_used);
+        }
     }
 
-    fn load_size_if_not_loaded(&mut self, font_size: u16, ttf_context: &'ttf sdl2::ttf::Sdl2TtfContext) {
-        if let None = self.stored_sizes.get(&font_size) {
-            self.stored_sizes.insert(font_size, ttf_context.load_font(self.file_name.clone(), font_size).unwrap());
+    // read the first time something needs it.
+    fn file_bytes(&mut self) -> Option<&[u8]> {
+        if self.broken {
+            return None;
+        }
+
+        if self.file_bytes.is_none() {
+            match std::fs::read(&self.file_name) {
+                Ok(file_bytes) => {
+                    self.file_bytes = Some(file_bytes.into_boxed_slice());
+                },
+                Err(error) => {
+                    println!("warning: couldn't load font \"{}\": {}", self.file_name, error);
+                    self.broken = true;
+                    return None;
+                }
+            }
         }
+
+        self.file_bytes.as_deref()
     }
 
-    fn get_size(&mut self, font_size: u16, ttf_context: &'ttf sdl2::ttf::Sdl2TtfContext) -> &sdl2::ttf::Font<'ttf, 'static> {
+    // The boxed bytes never move or change once read, and everything reading
+    // them is dropped before they are (see the field order), so pretending
+    // they're 'static is fine.
+    fn static_file_bytes(&mut self) -> Option<&'static [u8]> {
+        let file_bytes = self.file_bytes()?;
+        Some(unsafe { std::slice::from_raw_parts(file_bytes.as_ptr(), file_bytes.len()) })
+    }
+
+    fn parsed(&mut self) -> Option<&ParsedFont> {
+        if self.parsed.is_none() {
+            let file_bytes = self.static_file_bytes()?;
+            let face = rustybuzz::Face::from_slice(file_bytes, 0);
+            let font = ab_glyph::FontRef::try_from_slice(file_bytes).ok();
+            match face.zip(font) {
+                Some((face, font)) => self.parsed = Some(ParsedFont { face, font }),
+                None => {
+                    println!("warning: couldn't load font \"{}\": can't shape text with it", self.file_name);
+                    self.broken = true;
+                    return None;
+                }
+            }
+        }
+
+        self.parsed.as_ref()
+    }
+
+    fn get_size(&mut self, font_size: u16, ttf_context: &'ttf sdl2::ttf::Sdl2TtfContext) -> Option<&sdl2::ttf::Font<'ttf, 'static>> {
         self.load_size_if_not_loaded(font_size, ttf_context);
-        self.stored_sizes.get(&font_size).unwrap()
+        self.stored_sizes.get(&font_size)
     }
 
-    fn get_size_mut(&mut self, font_size: u16, ttf_context: &'ttf sdl2::ttf::Sdl2TtfContext) -> &mut sdl2::ttf::Font<'ttf, 'static> {
+    fn get_size_mut(&mut self, font_size: u16, ttf_context: &'ttf sdl2::ttf::Sdl2TtfContext) -> Option<&mut sdl2::ttf::Font<'ttf, 'static>> {
         self.load_size_if_not_loaded(font_size, ttf_context);
-        self.stored_sizes.get_mut(&font_size).unwrap()
+        self.stored_sizes.get_mut(&font_size)
     }
 }
 
@@ -108,24 +204,35 @@ impl SDL2ImageTextureAssets {
         self.images.get_mut(id)
     }
 
+    // Already loaded is fine, it's only an error if there's nothing to draw.
     fn insert<'a>(&mut self, path: &'a str) -> Result<&'a str, &'static str> {
         use sdl2::image::LoadSurface;
 
         if !self.images.contains_key(path) {
             let surface_image = sdl2::surface::Surface::from_file(path);
 
-            if let Ok(surface_image) = surface_image {
-                self.images.insert(path.to_owned(),
-                                   SDL2ImageTextureAsset{
-                                       texture: {
-                                           self.texture_creator.create_texture_from_surface(surface_image).unwrap()
-                                       }
-                                   });
+            match surface_image.map(|surface_image| self.texture_creator.create_texture_from_surface(surface_image)) {
+                Ok(Ok(texture)) => {
+                    self.images.insert(path.to_owned(), SDL2ImageTextureAsset{ texture });
+                },
+                _ => {
+                    return Err("Image couldn't be loaded?");
+                }
             }
+        }
 
-            Ok(path)
-        } else {
-            Err("Image couldn't be loaded?")
+        Ok(path)
+    }
+
+    fn remove(&mut self, path: &str) {
+        if let Some(image) = self.images.remove(path) {
+            unsafe{ image.texture.destroy(); }
+        }
+    }
+
+    fn clear(&mut self) {
+        for (_, image) in self.images.drain() {
+            unsafe{ image.texture.destroy(); }
         }
     }
 }
@@ -149,6 +256,15 @@ impl TextJustification {
         TextJustification(TextJustificationHorizontal::Center, TextJustificationVertical::Center)
     }
 }
+// How a string gets drawn, the line cache is separate. (render_static_text)
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextRenderer {
+    // the whole line as one texture, made and thrown away every draw.
+    Line,
+    // shaped, then drawn glyph by glyph out of a shared texture. For text that keeps changing.
+    GlyphAtlas,
+}
+
 // for text justification
 pub enum TextBounds {
     EntireScreen,
@@ -175,12 +291,84 @@ impl Default for Camera {
     }
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
-struct StaticTextCacheKey {
-    font_id: String,
-    text: String,
-    font_size: u16,
-    style: sdl2::ttf::FontStyle,
+// Remembered while drawing so mouse clicks can find out what they hit.
+// The rectangle is in window coordinates, since that's what mouse events use.
+pub struct ClickableRegion {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+    pub target: String,
+}
+
+// Shapes come out of the rasterizer once per size they're drawn at.
+struct ShapeTexture {
+    texture: sdl2::render::Texture,
+    left: f32,
+    top: f32,
+    last_used: u64,
+}
+
+// Everything that changes how a shape rasterizes. Floats can't be hashed, their bits can.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct ShapeTextureKey {
+    // which kind of shape, then its numbers.
+    shape: (u8, [u32; 5]),
+    fill: Option<[u8; 4]>,
+    stroke: Option<[u8; 4]>,
+    stroke_width: u32,
+    scale: u32,
+    visible: [u32; 4],
+}
+
+impl ShapeTextureKey {
+    fn new(shape: &Shape, style: &ShapeStyle, scale: f32, visible: (f32, f32, f32, f32)) -> ShapeTextureKey {
+        let (kind, numbers) = match *shape {
+            Shape::Rect { x, y, w, h, corner_radius } => (0, [x, y, w, h, corner_radius]),
+            Shape::Circle { x, y, radius } => (1, [x, y, radius, 0.0, 0.0]),
+            Shape::Line { x1, y1, x2, y2, arrow } => (if arrow { 3 } else { 2 }, [x1, y1, x2, y2, 0.0]),
+        };
+        let rgba = |color: Color| [color.r, color.g, color.b, color.a];
+        let (left, top, right, bottom) = visible;
+        ShapeTextureKey {
+            shape: (kind, numbers.map(f32::to_bits)),
+            fill: style.fill.map(rgba),
+            stroke: style.stroke.map(rgba),
+            stroke_width: style.stroke_width.to_bits(),
+            scale: scale.to_bits(),
+            visible: [left, top, right, bottom].map(f32::to_bits),
+        }
+    }
+}
+
+// Plenty for a few pages of diagrams at a couple of sizes (like the overview),
+// after that whichever was drawn longest ago goes.
+const MAX_SHAPE_TEXTURES : usize = 256;
+
+struct ShapeTextures {
+    textures: HashMap<ShapeTextureKey, ShapeTexture>,
+    clock: u64,
+}
+
+impl ShapeTextures {
+    fn new() -> ShapeTextures {
+        ShapeTextures { textures: HashMap::new(), clock: 0 }
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let least_recently_used = self.textures.iter()
+            .min_by_key(|(_, shape)| shape.last_used)
+            .map(|(key, _)| *key);
+        if let Some(shape) = least_recently_used.and_then(|key| self.textures.remove(&key)) {
+            unsafe{ shape.texture.destroy(); }
+        }
+    }
+
+    fn clear(&mut self) {
+        for (_, shape) in self.textures.drain() {
+            unsafe{ shape.texture.destroy(); }
+        }
+    }
 }
 
 pub struct SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
@@ -190,13 +378,19 @@ pub struct SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
     video_subsystem: &'sdl2 sdl2::VideoSubsystem,
 
     white_rectangle_texture: SDL2ImageTextureAsset,
-    static_text_texture_cache: HashMap<StaticTextCacheKey, sdl2::render::Texture>,
+    static_text_texture_cache: TextTextureCache,
+    glyph_atlas: GlyphAtlas,
+    text_shaper: TextShaper,
     font_assets : HashMap<String, SDL2FontAsset<'ttf>>,
     image_assets : SDL2ImageTextureAssets,
+    shape_textures : ShapeTextures,
+    clickable_regions : Vec<ClickableRegion>,
 
     // camera should probably not be public?
     pub camera: Camera,
     pub logical_resolution : VirtualResolution,
+    // for whatever text is drawn next. Auto looks at each string by itself.
+    pub text_direction: TextDirection,
 }
 
 // lots of interface and safety changes to be made.
@@ -229,11 +423,16 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
             image_context,
             video_subsystem,
             font_assets: HashMap::new(),
-            static_text_texture_cache: HashMap::new(),
+            static_text_texture_cache: TextTextureCache::new(DEFAULT_TEXT_CACHE_BUDGET_BYTES),
+            glyph_atlas: GlyphAtlas::new(),
+            text_shaper: TextShaper::new(),
             image_assets: SDL2ImageTextureAssets::new(texture_creator),
+            shape_textures: ShapeTextures::new(),
+            clickable_regions: Vec::new(),
             white_rectangle_texture: SDL2ImageTextureAsset{ texture: white_texture },
             camera: Camera::default(),
             logical_resolution: VirtualResolution::Display,
+            text_direction: TextDirection::Auto,
         }
     }
 
@@ -252,6 +451,24 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
         self.window_canvas.set_viewport(None);
     }
 
+    // Rectangle is in logical coordinates, and does not care about the camera.
+    pub fn set_clip_rectangle(&mut self, rectangle: Option<(f32, f32, f32, f32)>) {
+        match rectangle {
+            Some((x, y, w, h)) => {
+                let (x, y) = self.scale_xy_pair_to_real(x, y);
+                let (w, h) = self.scale_xy_pair_to_real(w, h);
+                self.window_canvas.set_clip_rect(
+                    Some(sdl2::rect::Rect::new(x as i32,
+                                               y as i32,
+                                               w as u32,
+                                               h as u32)));
+            },
+            None => {
+                self.window_canvas.set_clip_rect(None);
+            }
+        }
+    }
+
     pub fn enable_alpha_blending(&mut self) {
         self.window_canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
     }
@@ -281,12 +498,11 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
         self.image_assets.insert(image_file_name)
     }
 
-    // TODO(jerry): Fallback font?
+    // Fallback fonts are per slideshow, see split_text_by_font_coverage.
     pub fn add_font<'a>(&mut self, font_name: &'a str) -> &'a str {
         if !self.font_assets.contains_key(font_name) {
             self.font_assets.insert(font_name.to_owned(),
-                                    SDL2FontAsset::new_with_common_sizes(font_name.to_owned(),
-                                                                         &self.ttf_context));
+                                    SDL2FontAsset::new(font_name.to_owned()));
         }
         font_name
     }
@@ -315,6 +531,14 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
         }
     }
 
+    pub fn is_fullscreen(&self) -> bool {
+        use sdl2::video::FullscreenType;
+        match self.window().fullscreen_state() {
+            FullscreenType::Off => false,
+            FullscreenType::True | FullscreenType::Desktop => true,
+        }
+    }
+
     pub fn toggle_fullscreen(&mut self) {
         use sdl2::video::FullscreenType;
         let fullscreen_state = self.window().fullscreen_state();
@@ -409,7 +633,7 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
         let font_asset = self.get_font_asset_mut(font_id);
 
         if let Some(font_asset) = font_asset {
-            Some(font_asset.get_size(font_size, ttf_context))
+            font_asset.get_size(font_size, ttf_context)
         } else {
             None
         }
@@ -420,12 +644,66 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
         let font_asset = self.get_font_asset_mut(font_id);
 
         if let Some(font_asset) = font_asset {
-            Some(font_asset.get_size_mut(font_size, ttf_context))
+            font_asset.get_size_mut(font_size, ttf_context)
         } else {
             None
         }
     }
 
+    // Cuts text into runs that can each be drawn with one font, the first one in
+    // font_id then fallback_fonts that has the character. Characters nobody has stay
+    // with font_id, and spaces stay with whatever run they're in.
+    pub fn split_text_by_font_coverage<'a, 'b>(&mut self,
+                                               font_id: &'a str,
+                                               fallback_fonts: &'a [String],
+                                               text: &'b str,
+                                               font_size: u16) -> Vec<(&'a str, &'b str)> {
+        if fallback_fonts.is_empty() {
+            return vec![(font_id, text)];
+        }
+
+        let mut runs = Vec::new();
+        let mut run_start = 0;
+        let mut run_font = font_id;
+
+        for (index, character) in text.char_indices() {
+            if character.is_whitespace() {
+                continue;
+            }
+
+            let character_font = self.first_font_with_glyph(font_id, fallback_fonts, character, font_size);
+            if character_font != run_font {
+                if index > run_start {
+                    runs.push((run_font, &text[run_start..index]));
+                    run_start = index;
+                }
+                run_font = character_font;
+            }
+        }
+
+        if run_start < text.len() {
+            runs.push((run_font, &text[run_start..]));
+        }
+        runs
+    }
+
+    fn first_font_with_glyph<'a>(&mut self,
+                                 font_id: &'a str,
+                                 fallback_fonts: &'a [String],
+                                 character: char,
+                                 font_size: u16) -> &'a str {
+        std::iter::once(font_id)
+            .chain(fallback_fonts.iter().map(|font| font.as_str()))
+            .find(
+                |candidate_font| {
+                    self.add_font(candidate_font);
+                    self.find_text_asset_by_size(candidate_font, font_size)
+                        .map(|font| font.find_glyph(character).is_some())
+                        .unwrap_or(false)
+                })
+            .unwrap_or(font_id)
+    }
+
     // Please check for whether this image actually exists for real.
     pub fn image_dimensions(&self, texture_image: &str) -> Option<(u32, u32)> {
         if let Some(texture_image) = self.get_image_asset(texture_image) {
@@ -497,6 +775,13 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
         }
     }
 
+    // For mouse input, this undoes the letterboxing so window coordinates
+    // line up with what was drawn in the current logical resolution.
+    pub fn window_xy_pair_to_logical(&self, x: i32, y: i32) -> (f32, f32) {
+        let (offset_x, offset_y) = self.scale_and_transform_xy_pair_to_real(0.0, 0.0);
+        self.scale_xy_pair_to_logical(x as f32 - offset_x, y as f32 - offset_y)
+    }
+
     fn scale_xy_pair_to_logical(&self, x: f32, y: f32) -> (f32, f32) {
         let scaling_factor = self.aspect_ratio_scale_factor();
         (x / scaling_factor, y / scaling_factor)
@@ -521,106 +806,125 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
     // this is a literal text dimensions. This doesn't
     // account for the virtual resolution system.
     pub fn text_dimensions(&mut self, font_id: &str, text: &str, font_size: u16) -> (u32, u32) {
-        if let Some(font_at_size) = self.find_text_asset_by_size(font_id, font_size) {
-            let (width, height) = font_at_size.size_of(text).unwrap();
-            (width, height)
-        } else {
-            (0, 0)
+        let height = self.font_height(font_id, font_size);
+        if height == 0 {
+            return (0, 0);
         }
+
+        let direction = self.text_direction;
+        let &mut SDL2GraphicsContext { ref mut font_assets, ref mut text_shaper, .. } = self;
+        let width = font_assets.get_mut(font_id)
+            .and_then(|font| font.parsed())
+            .map(|parsed| text_shaper.shape(&parsed.face, text, font_size, direction).1)
+            .unwrap_or(0.0);
+        (width.ceil() as u32, height)
+    }
+
+    // Line height without shaping anything.
+    pub fn font_height(&mut self, font_id: &str, font_size: u16) -> u32 {
+        self.find_text_asset_by_size(font_id, font_size)
+            .map(|font_at_size| font_at_size.height() as u32)
+            .unwrap_or(0)
+    }
+
+    // From the top of a line to its baseline.
+    pub fn font_ascent(&mut self, font_id: &str, font_size: u16) -> f32 {
+        self.find_text_asset_by_size(font_id, font_size)
+            .map(|font_at_size| font_at_size.ascent() as f32)
+            .unwrap_or(0.0)
     }
 
     pub fn scale_font_size(&self, font_size: u16) -> u16 {
         (font_size as f32 * self.aspect_ratio_scale_factor()) as u16
     }
 
+    // Shaped, so ligatures and right to left text come out right, but SDL_ttf's
+    // ascent and height so lines sit where they always have.
     fn draw_string_texture(&mut self,
                            font_id: &str,
                            text: &str,
                            font_size: u16,
                            style: sdl2::ttf::FontStyle) -> Option<sdl2::render::Texture> {
         let texture_creator = self.window_canvas.texture_creator();
-        match self.find_text_asset_by_size_mut(font_id, font_size) {
-            Some(font) => {
-                if font.get_style() != style {
-                    font.set_style(style);
-                }
-                let font_surface = font.render(text)
-                    .blended(SDLColor::RGBA(255, 255, 255, 255))
-                    .expect("how did this go wrong?");
-
-                let texture = texture_creator.create_texture_from_surface(
-                    &font_surface
-                ).expect("how did this go wrong?");
-                std::mem::drop(font_surface);
-                Some(texture)
-            },
-            None => { None }
+        let direction = self.text_direction;
+        let (ascent, height) = {
+            let font = self.find_text_asset_by_size(font_id, font_size)?;
+            (font.ascent() as f32, font.height() as u32)
+        };
+
+        let &mut SDL2GraphicsContext { ref mut font_assets, ref mut text_shaper, .. } = self;
+        let parsed = font_assets.get_mut(font_id)?.parsed()?;
+
+        let (glyphs, advance) = text_shaper.shape(&parsed.face, text, font_size, direction);
+        let width = advance.ceil() as u32 + synthetic_style_overhang(font_size, ascent, style);
+        // empty text has nothing to draw, which is fine.
+        if glyphs.is_empty() || width == 0 || height == 0 {
+            return None;
         }
+
+        let pixels = rasterize_glyphs(&parsed.font, glyphs, font_size, ascent, style, width, height);
+        let mut texture = texture_creator.create_texture_static(sdl2::pixels::PixelFormatEnum::RGBA32, width, height).ok()?;
+        texture.update(None, &pixels, (width * 4) as usize).ok()?;
+        Some(texture)
     }
 
+    // only draws the text if it isn't cached already.
     fn load_cached_string_texture(&mut self,
                                   font_id: &str,
                                   text: &str,
                                   font_size: u16,
-                                  style: sdl2::ttf::FontStyle) -> Option<&mut sdl2::render::Texture> {
-        // is &str hashable?
-        let key =
-            StaticTextCacheKey {
-                font_id: font_id.to_owned(),
-                text: text.to_owned(),
-                font_size,
-                style,
-            };
-        let is_already_in_cache = self.static_text_texture_cache.get(&key).is_some();
+                                  style: sdl2::ttf::FontStyle) -> bool {
+        let key = TextTextureKey { font_id, text, font_size, style, direction: self.text_direction };
+        if self.static_text_texture_cache.get(&key).is_some() {
+            return true;
+        }
 
-        if is_already_in_cache {
-            self.static_text_texture_cache.get_mut(&key)
-        } else {
-            let new_texture_to_cache = self.draw_string_texture(font_id, text, font_size, style);
-            match new_texture_to_cache {
-                Some(texture) => {
-                    #[cfg(debug_assertions)]
-                    println!("inserting");
-                    self.static_text_texture_cache.insert(key.clone(), texture);
-                    self.static_text_texture_cache.get_mut(&key)
-                },
-                None => None
-            }
+        match self.draw_string_texture(font_id, text, font_size, style) {
+            Some(texture) => {
+                self.static_text_texture_cache.insert(&key, texture);
+                true
+            },
+            None => false
         }
     }
 
-    fn get_cached_string_texture(&self,
-                                  font_id: &str,
-                                  text: &str,
-                                  font_size: u16,
-                                  style: sdl2::ttf::FontStyle) -> Option<&sdl2::render::Texture> {
-        // is &str hashable?
-        let key =
-            StaticTextCacheKey {
-                font_id: font_id.to_owned(),
-                text: text.to_owned(),
-                font_size,
-                style,
-            };
-        self.static_text_texture_cache.get(&key)
+    pub fn set_text_cache_budget(&mut self, budget_bytes: usize) {
+        self.static_text_texture_cache.set_budget_bytes(budget_bytes);
+    }
+
+    pub fn text_cache_stats(&self) -> TextTextureCacheStats {
+        self.static_text_texture_cache.stats()
     }
 
     pub fn clear_font_cache(&mut self) {
         self.font_assets.clear();
+        self.glyph_atlas.clear();
     }
 
     pub fn clear_static_string_cache(&mut self) {
-        {
-            let drained = self.static_text_texture_cache.drain();
-            for (_, texture) in drained {
-                unsafe{ texture.destroy(); }
-            }
-        }
+        self.static_text_texture_cache.clear();
+    }
+
+    pub fn clear_image_cache(&mut self) {
+        self.image_assets.clear();
     }
 
     pub fn clear_resources(&mut self) {
         self.clear_font_cache();
         self.clear_static_string_cache();
+        self.clear_image_cache();
+        self.shape_textures.clear();
+    }
+
+    // For when one file changed on disk. Only throws away what came from it, text
+    // drawn in some other font stays cached.
+    pub fn invalidate_file(&mut self, file_name: &str) {
+        self.image_assets.remove(file_name);
+
+        if self.font_assets.remove(file_name).is_some() {
+            self.static_text_texture_cache.remove_font(file_name);
+            self.glyph_atlas.remove_font(file_name);
+        }
     }
 
     pub fn render_static_text(&mut self,
@@ -635,28 +939,18 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
         let (x, y) = self.scale_xy_pair_to_real((x * self.camera.scale) + self.camera.x,
                                                 (y * self.camera.scale) + self.camera.y);
 
-        {
-            let mut text_texture = self.load_cached_string_texture(font_id, text, font_size, style);
-            match &mut text_texture {
-                Some(text_texture) => {
-                    text_texture.set_blend_mode(sdl2::render::BlendMode::Blend);
-                    text_texture.set_color_mod(color.r, color.g, color.b);
-                    text_texture.set_alpha_mod(color.a);
-                },
-                None => {},
-            }
+        if !self.load_cached_string_texture(font_id, text, font_size, style) {
+            return 0.0;
         }
+        let key = TextTextureKey { font_id, text, font_size, style, direction: self.text_direction };
         let &mut SDL2GraphicsContext { ref mut window_canvas,
-                                       ref static_text_texture_cache, .. } = self;
+                                       ref mut static_text_texture_cache, .. } = self;
         {
-            let text_texture = static_text_texture_cache.get(
-                &StaticTextCacheKey {
-                    font_id: font_id.to_owned(),
-                    text: text.to_owned(),
-                    font_size,
-                    style,
-                }
-            ).unwrap();
+            let text_texture = static_text_texture_cache.peek_mut(&key).unwrap();
+            text_texture.set_blend_mode(sdl2::render::BlendMode::Blend);
+            text_texture.set_color_mod(color.r, color.g, color.b);
+            text_texture.set_alpha_mod(color.a);
+
             let sdl2::render::TextureQuery { width, height, .. } = text_texture.query();
             window_canvas.copy(&text_texture, None,
                                Some(sdl2::rect::Rect::new(x as i32,
@@ -667,6 +961,31 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
         }
     }
 
+    // How wide render_static_text will draw this, so right to left text can be laid
+    // out before it's drawn. It's cached right away, since it's about to be drawn anyway.
+    // Measuring only peeks, the draw afterwards is what counts as a use.
+    pub fn static_text_width(&mut self,
+                             font_id: &str,
+                             text: &str,
+                             font_size: u16,
+                             style: sdl2::ttf::FontStyle) -> f32 {
+        let font_size = self.scale_font_size((font_size as f32 * self.camera.scale) as u16);
+        let key = TextTextureKey { font_id, text, font_size, style, direction: self.text_direction };
+
+        if self.static_text_texture_cache.peek_mut(&key).is_none() {
+            match self.draw_string_texture(font_id, text, font_size, style) {
+                Some(texture) => self.static_text_texture_cache.insert(&key, texture),
+                None => return 0.0,
+            }
+        }
+
+        let width = self.static_text_texture_cache.peek_mut(&key)
+            .map(|text_texture| text_texture.query().width)
+            .unwrap_or(0);
+        self.scale_xy_pair_to_logical(width as f32, 0.0).0
+    }
+
+    // For anything that changes from frame to frame, timers and counters and such.
     pub fn render_text(&mut self,
                        font_id: &str,
                        x: f32,
@@ -675,10 +994,43 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
                        font_size: u16,
                        color: Color,
                        style: sdl2::ttf::FontStyle) -> f32 {
+        self.render_text_using(TextRenderer::GlyphAtlas, font_id, x, y, text, font_size, color, style)
+    }
+
+    pub fn render_text_using(&mut self,
+                             renderer: TextRenderer,
+                             font_id: &str,
+                             x: f32,
+                             y: f32,
+                             text: &str,
+                             font_size: u16,
+                             color: Color,
+                             style: sdl2::ttf::FontStyle) -> f32 {
         let font_size = self.scale_font_size((font_size as f32 * self.camera.scale) as u16);
         let (x, y) = self.scale_xy_pair_to_real((x * self.camera.scale) + self.camera.x,
                                                 (y * self.camera.scale) + self.camera.y);
 
+        // The atlas only has the plain glyphs, styled text is rasterized as a whole line (see rasterize_glyphs).
+        if renderer == TextRenderer::GlyphAtlas && style == sdl2::ttf::FontStyle::NORMAL {
+            let direction = self.text_direction;
+            let ascent = self.find_text_asset_by_size(font_id, font_size).map(|font| font.ascent() as f32);
+            let &mut SDL2GraphicsContext { ref mut window_canvas,
+                                           ref mut font_assets,
+                                           ref mut glyph_atlas,
+                                           ref mut text_shaper, .. } = self;
+            let width = ascent.and_then(
+                |ascent| {
+                    let parsed = font_assets.get_mut(font_id)?.parsed()?;
+                    let (glyphs, width) = text_shaper.shape(&parsed.face, text, font_size, direction);
+                    glyph_atlas.render_glyphs(window_canvas, &parsed.font, font_id,
+                                              GlyphRun { glyphs, x, y: y + ascent, font_size, color });
+                    Some(width)
+                });
+            if let Some(width) = width {
+                return self.scale_xy_pair_to_logical(width, 0.0).0;
+            }
+        }
+
         match self.draw_string_texture(font_id, text, font_size, style) {
             Some(mut text_texture) => {
                 text_texture.set_blend_mode(sdl2::render::BlendMode::Blend);
@@ -695,7 +1047,7 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
                 unsafe{text_texture.destroy();}
                 return self.scale_xy_pair_to_logical(width as f32, 0.0).0;
             },
-            None => { panic!("!"); }
+            None => { 0.0 }
         }
     }
 
@@ -729,7 +1081,86 @@ impl<'sdl2, 'ttf, 'image> SDL2GraphicsContext<'sdl2, 'ttf, 'image> {
                 TextJustificationVertical::Center => (h / 2.0) - (height as f32 / 2.0) + y,
             }
         };
-        self.render_text(font_id, x, y, text, font_size, color, style)
+        // these are titles and headings, glyphs that big would crowd everything else out of the atlas.
+        self.render_text_using(TextRenderer::Line, font_id, x, y, text, font_size, color, style)
+    }
+
+    // Same coordinates as anything else drawn, so it follows the camera and viewport.
+    pub fn add_clickable_region(&mut self, x: f32, y: f32, w: f32, h: f32, target: &str) {
+        let (x, y) = self.scale_xy_pair_to_real((x * self.camera.scale) + self.camera.x,
+                                                (y * self.camera.scale) + self.camera.y);
+        let (w, h) = self.scale_xy_pair_to_real(w * self.camera.scale, h * self.camera.scale);
+        let viewport = self.window_canvas.viewport();
+
+        self.clickable_regions.push(
+            ClickableRegion {
+                x: x as i32 + viewport.x(),
+                y: y as i32 + viewport.y(),
+                w: w as u32,
+                h: h as u32,
+                target: target.to_owned(),
+            });
+    }
+
+    pub fn clickable_region_at(&self, x: i32, y: i32) -> Option<&ClickableRegion> {
+        // last drawn is on top.
+        self.clickable_regions.iter().rev().find(
+            |region| {
+                x >= region.x && x < region.x + region.w as i32 &&
+                y >= region.y && y < region.y + region.h as i32
+            })
+    }
+
+    pub fn clear_clickable_regions(&mut self) {
+        self.clickable_regions.clear();
+    }
+
+    // Antialiased, unlike anything SDL draws by itself.
+    pub fn render_shape(&mut self, shape: &Shape, style: &ShapeStyle) {
+        let scale = self.camera.scale * self.aspect_ratio_scale_factor();
+        // the slide, anything outside of it isn't seen anyway.
+        let visible = (0.0, 0.0, self.logical_width() as f32, self.logical_height() as f32);
+        let key = ShapeTextureKey::new(shape, style, scale, visible);
+
+        if !self.shape_textures.textures.contains_key(&key) {
+            let rasterized = rasterize_shape(shape, style, scale, visible);
+            if rasterized.pixels.is_empty() {
+                return;
+            }
+            let texture_creator = self.window_canvas.texture_creator();
+            let mut texture = match texture_creator.create_texture_static(sdl2::pixels::PixelFormatEnum::RGBA32,
+                                                                          rasterized.width, rasterized.height) {
+                Ok(texture) => texture,
+                Err(_) => return,
+            };
+            if texture.update(None, &rasterized.pixels, rasterized.width as usize * 4).is_err() {
+                unsafe{ texture.destroy(); }
+                return;
+            }
+            texture.set_blend_mode(sdl2::render::BlendMode::Blend);
+
+            if self.shape_textures.textures.len() >= MAX_SHAPE_TEXTURES {
+                self.shape_textures.evict_least_recently_used();
+            }
+            self.shape_textures.textures.insert(key, ShapeTexture { texture, left: rasterized.left, top: rasterized.top, last_used: 0 });
+        }
+
+        self.shape_textures.clock += 1;
+        let clock = self.shape_textures.clock;
+        if let Some(shape_texture) = self.shape_textures.textures.get_mut(&key) {
+            shape_texture.last_used = clock;
+        }
+
+        let shape_texture = &self.shape_textures.textures[&key];
+        let (x, y) = self.scale_xy_pair_to_real((shape_texture.left * self.camera.scale) + self.camera.x,
+                                                (shape_texture.top * self.camera.scale) + self.camera.y);
+        let query = shape_texture.texture.query();
+        self.window_canvas.copy(&shape_texture.texture, None,
+                                Some(sdl2::rect::Rect::new(x.round() as i32,
+                                                           y.round() as i32,
+                                                           query.width,
+                                                           query.height)))
+            .unwrap();
     }
 
     pub fn render_filled_rectangle(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color) {
@@ -9,24 +9,38 @@ impl ApplicationScreenState for InvalidOrNoSlideState {
         for event in event_pump.poll_iter() {
             match event {
                 SDLEvent::DropFile { filename, .. } => {
-                    graphics_context.clear_resources();
-
-                    let new_slide = Slide::new_from_file(&filename);
-                    app.slideshow = new_slide;
-
-                    app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                    app.open_slideshow(graphics_context, &filename);
                 },
                 SDLEvent::Quit {..} => {
                     app.state = ApplicationScreen::Quit(QuitState);
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Escape), .. } =>  {
-                    app.state = ApplicationScreen::Quit(QuitState);
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::O), .. } => {
-                    app.state = ApplicationScreen::Options(OptionsState);
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } if keycode_to_digit(keycode).is_some() => {
+                    let index = keycode_to_digit(keycode).and_then(|digit| digit.to_digit(10)).unwrap_or(0) as usize;
+                    // listed from 1, so 0 doesn't open anything.
+                    let recent_file = index.checked_sub(1).and_then(|index| app.recent_files.get(index)).cloned();
+                    if let Some(recent_file) = recent_file {
+                        if let Some(file_name) = recent_file.path.to_str() {
+                            app.open_slideshow(graphics_context, file_name);
+                        }
+                    }
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::L), .. } => {
-                    app.state = ApplicationScreen::SelectSlideToLoad(SelectSlideToLoadState);
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } => {
+                    match app.keymap.action(keycode) {
+                        // nothing to go back to from here.
+                        Some(InputAction::Cancel) | Some(InputAction::Quit) => {
+                            app.state = ApplicationScreen::Quit(QuitState);
+                        },
+                        Some(InputAction::ToggleFullscreen) => {
+                            app.toggle_fullscreen(graphics_context);
+                        },
+                        Some(InputAction::Options) => {
+                            app.state = ApplicationScreen::Options(OptionsState);
+                        },
+                        Some(InputAction::OpenBrowser) => {
+                            app.state = ApplicationScreen::SelectSlideToLoad(SelectSlideToLoadState);
+                        },
+                        _ => {}
+                    }
                 },
                 _ => {
                     #[cfg(debug_assertions)]
@@ -39,17 +53,54 @@ impl ApplicationScreenState for InvalidOrNoSlideState {
     }
 
     fn draw(&self,
-            _app: &ApplicationState,
+            app: &ApplicationState,
             graphics_context: &mut SDL2GraphicsContext) {
-        let default_font = graphics_context.add_font("data/fonts/libre-baskerville/LibreBaskerville-Regular.ttf");
+        let default_font = graphics_context.add_font(&app.settings.default_font);
         graphics_context.clear_color(Color::new(10, 10, 16, 255));
         graphics_context.logical_resolution = VirtualResolution::Display;
+
+        if app.recent_files.files().is_empty() {
+            graphics_context.render_text_justified(default_font,
+                                                   TextBounds::EntireScreen,
+                                                   TextJustification::center(),
+                                                   "Invalid / No slide file",
+                                                   graphics_context.font_size_percent(0.073),
+                                                   COLOR_WHITE,
+                                                   sdl2::ttf::FontStyle::NORMAL);
+            return;
+        }
+
+        let title_font_size = graphics_context.font_size_percent(0.073);
+        let mut draw_cursor_y = title_font_size as f32;
         graphics_context.render_text_justified(default_font,
-                                               TextBounds::EntireScreen,
+                                               TextBounds::ScreenLine(0.0, draw_cursor_y),
                                                TextJustification::center(),
                                                "Invalid / No slide file",
-                                               graphics_context.font_size_percent(0.073),
+                                               title_font_size,
                                                COLOR_WHITE,
                                                sdl2::ttf::FontStyle::NORMAL);
+        draw_cursor_y += title_font_size as f32 * 2.0;
+
+        let font_size = graphics_context.font_size_percent(0.045);
+        let margin = graphics_context.logical_width() as f32 * 0.15;
+        graphics_context.render_text(default_font,
+                                     margin, draw_cursor_y,
+                                     "Recent slideshows",
+                                     font_size,
+                                     Color::new(128, 128, 128, 255),
+                                     sdl2::ttf::FontStyle::NORMAL);
+        draw_cursor_y += font_size as f32 * 1.5;
+
+        for (index, recent_file) in app.recent_files.files().iter().enumerate() {
+            // still listed, since it might just be an unmounted drive.
+            let color = if recent_file.exists() { COLOR_WHITE } else { Color::new(128, 128, 128, 255) };
+            graphics_context.render_text(default_font,
+                                         margin, draw_cursor_y,
+                                         &format!("{}   {}  (page {})", index + 1, recent_file.display_name(), recent_file.last_page + 1),
+                                         font_size,
+                                         color,
+                                         sdl2::ttf::FontStyle::NORMAL);
+            draw_cursor_y += font_size as f32 * 1.2;
+        }
     }
 }
@@ -0,0 +1,253 @@
+/*
+    Key bindings.
+
+    States never look at keycodes directly (except for typing numbers), they
+    ask the keymap what the key means and react to the action instead. This is
+    so people with clickers or weird keyboards can rebind everything from
+
+        <config directory>/slideshow/keys.conf
+
+    which looks like
+
+        # action = key, key, ...
+        next_page = Right, PageDown, Space
+        quit = Q
+
+    Key names are whatever SDL calls them. Binding an action in the file replaces
+    all of its default keys, anything not mentioned keeps the defaults.
+*/
+use std::collections::HashMap;
+use sdl2::keyboard::Keycode as SDLKeycode;
+
+pub const KEYMAP_FILE_NAME : &str = "keys.conf";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    NextPage,
+    PreviousPage,
+    FirstPage,
+    LastPage,
+    SelectionUp,
+    SelectionDown,
+    Accept,
+    Back,
+    Cancel,
+    ToggleFullscreen,
+    ToggleDebugOverlay,
+    Reload,
+    OpenBrowser,
+    Options,
+    Presenter,
+    Overview,
+    BlackScreen,
+    WhiteScreen,
+    ResetTimer,
+    CloseSlideshow,
+    Quit,
+}
+
+const ALL_INPUT_ACTIONS : [InputAction; 21] = [
+    InputAction::NextPage,
+    InputAction::PreviousPage,
+    InputAction::FirstPage,
+    InputAction::LastPage,
+    InputAction::SelectionUp,
+    InputAction::SelectionDown,
+    InputAction::Accept,
+    InputAction::Back,
+    InputAction::Cancel,
+    InputAction::ToggleFullscreen,
+    InputAction::ToggleDebugOverlay,
+    InputAction::Reload,
+    InputAction::OpenBrowser,
+    InputAction::Options,
+    InputAction::Presenter,
+    InputAction::Overview,
+    InputAction::BlackScreen,
+    InputAction::WhiteScreen,
+    InputAction::ResetTimer,
+    InputAction::CloseSlideshow,
+    InputAction::Quit,
+];
+
+impl InputAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::NextPage => "next_page",
+            InputAction::PreviousPage => "previous_page",
+            InputAction::FirstPage => "first_page",
+            InputAction::LastPage => "last_page",
+            InputAction::SelectionUp => "selection_up",
+            InputAction::SelectionDown => "selection_down",
+            InputAction::Accept => "accept",
+            InputAction::Back => "back",
+            InputAction::Cancel => "cancel",
+            InputAction::ToggleFullscreen => "toggle_fullscreen",
+            InputAction::ToggleDebugOverlay => "toggle_debug_overlay",
+            InputAction::Reload => "reload",
+            InputAction::OpenBrowser => "open_browser",
+            InputAction::Options => "options",
+            InputAction::Presenter => "presenter",
+            InputAction::Overview => "overview",
+            InputAction::BlackScreen => "black_screen",
+            InputAction::WhiteScreen => "white_screen",
+            InputAction::ResetTimer => "reset_timer",
+            InputAction::CloseSlideshow => "close_slideshow",
+            InputAction::Quit => "quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<InputAction> {
+        ALL_INPUT_ACTIONS.iter().find(|action| action.name() == name).copied()
+    }
+
+    fn default_keys(&self) -> &'static [SDLKeycode] {
+        match self {
+            InputAction::NextPage => &[SDLKeycode::Right, SDLKeycode::PageDown, SDLKeycode::Space],
+            InputAction::PreviousPage => &[SDLKeycode::Left, SDLKeycode::PageUp],
+            InputAction::FirstPage => &[SDLKeycode::Home],
+            InputAction::LastPage => &[SDLKeycode::End],
+            InputAction::SelectionUp => &[SDLKeycode::Up],
+            InputAction::SelectionDown => &[SDLKeycode::Down],
+            InputAction::Accept => &[SDLKeycode::Return, SDLKeycode::KpEnter],
+            InputAction::Back => &[SDLKeycode::Backspace],
+            InputAction::Cancel => &[SDLKeycode::Escape],
+            InputAction::ToggleFullscreen => &[SDLKeycode::F],
+            InputAction::ToggleDebugOverlay => &[SDLKeycode::F3],
+            InputAction::Reload => &[SDLKeycode::R],
+            InputAction::OpenBrowser => &[SDLKeycode::L],
+            InputAction::Options => &[SDLKeycode::O],
+            InputAction::Presenter => &[SDLKeycode::P],
+            InputAction::Overview => &[SDLKeycode::Tab, SDLKeycode::G],
+            InputAction::BlackScreen => &[SDLKeycode::B, SDLKeycode::Period],
+            InputAction::WhiteScreen => &[SDLKeycode::W],
+            InputAction::ResetTimer => &[SDLKeycode::T],
+            InputAction::CloseSlideshow => &[SDLKeycode::C],
+            InputAction::Quit => &[SDLKeycode::Q],
+        }
+    }
+}
+
+// Numbers are typed, not bound, so these are the same for everyone.
+pub fn keycode_to_digit(keycode: SDLKeycode) -> Option<char> {
+    match keycode {
+        SDLKeycode::Num0 | SDLKeycode::Kp0 => Some('0'),
+        SDLKeycode::Num1 | SDLKeycode::Kp1 => Some('1'),
+        SDLKeycode::Num2 | SDLKeycode::Kp2 => Some('2'),
+        SDLKeycode::Num3 | SDLKeycode::Kp3 => Some('3'),
+        SDLKeycode::Num4 | SDLKeycode::Kp4 => Some('4'),
+        SDLKeycode::Num5 | SDLKeycode::Kp5 => Some('5'),
+        SDLKeycode::Num6 | SDLKeycode::Kp6 => Some('6'),
+        SDLKeycode::Num7 | SDLKeycode::Kp7 => Some('7'),
+        SDLKeycode::Num8 | SDLKeycode::Kp8 => Some('8'),
+        SDLKeycode::Num9 | SDLKeycode::Kp9 => Some('9'),
+        _ => None,
+    }
+}
+
+pub struct Keymap {
+    bindings: HashMap<SDLKeycode, InputAction>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let mut keymap = Keymap { bindings: HashMap::new() };
+        for action in ALL_INPUT_ACTIONS.iter() {
+            for keycode in action.default_keys() {
+                keymap.bind(*keycode, *action);
+            }
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    pub fn action(&self, keycode: SDLKeycode) -> Option<InputAction> {
+        self.bindings.get(&keycode).copied()
+    }
+
+    pub fn bind(&mut self, keycode: SDLKeycode, action: InputAction) {
+        self.bindings.insert(keycode, action);
+    }
+
+    pub fn unbind_action(&mut self, action: InputAction) {
+        self.bindings.retain(|_, bound_action| *bound_action != action);
+    }
+
+    // Bad lines are reported and skipped, a typo shouldn't stop the show.
+    pub fn new_from_source(source: &str) -> Keymap {
+        Keymap::new_from_source_with_key_names(source, SDLKeycode::from_name)
+    }
+
+    // SDL's key name lookup wants SDL to be up, so tests bring their own.
+    fn new_from_source_with_key_names<F: Fn(&str) -> Option<SDLKeycode>>(source: &str, key_from_name: F) -> Keymap {
+        let mut keymap = Keymap::default();
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let action_name = parts.next().unwrap_or("").trim();
+            let key_names = parts.next();
+
+            match (InputAction::from_name(action_name), key_names) {
+                (Some(action), Some(key_names)) => {
+                    keymap.unbind_action(action);
+                    for key_name in key_names.split(',').map(|key_name| key_name.trim()) {
+                        if key_name.is_empty() {
+                            continue;
+                        }
+
+                        match key_from_name(key_name) {
+                            Some(keycode) => keymap.bind(keycode, action),
+                            None => println!("warning: {}:{}: unknown key \"{}\"", KEYMAP_FILE_NAME, line_number+1, key_name),
+                        }
+                    }
+                },
+                (None, _) => {
+                    println!("warning: {}:{}: unknown action \"{}\"", KEYMAP_FILE_NAME, line_number+1, action_name);
+                },
+                (_, None) => {
+                    println!("warning: {}:{}: expected action = keys", KEYMAP_FILE_NAME, line_number+1);
+                },
+            }
+        }
+
+        keymap
+    }
+
+    pub fn load() -> Keymap {
+        use crate::utility::*;
+        let keymap_path = user_config_directory().map(|directory| directory.join(KEYMAP_FILE_NAME));
+
+        match keymap_path.as_ref().and_then(|path| path.to_str()).map(load_file) {
+            Some(Ok(source)) => Keymap::new_from_source(&source),
+            _ => Keymap::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn keymap_defaults() {
+    let keymap = Keymap::default();
+    assert_eq!(Some(InputAction::NextPage), keymap.action(SDLKeycode::Space));
+    assert_eq!(Some(InputAction::Cancel), keymap.action(SDLKeycode::Escape));
+    assert_eq!(None, keymap.action(SDLKeycode::Z));
+}
+#[test]
+fn keymap_rebinding_replaces_defaults() {
+    let key_from_name = |name: &str| match name {
+        "N" => Some(SDLKeycode::N),
+        "PageDown" => Some(SDLKeycode::PageDown),
+        _ => None,
+    };
+    let keymap = Keymap::new_from_source_with_key_names("# comment\nnext_page = N, PageDown, NoSuchKey\nnot_an_action = X\n", key_from_name);
+    assert_eq!(Some(InputAction::NextPage), keymap.action(SDLKeycode::N));
+    assert_eq!(Some(InputAction::NextPage), keymap.action(SDLKeycode::PageDown));
+    assert_eq!(None, keymap.action(SDLKeycode::Right));
+    assert_eq!(Some(InputAction::PreviousPage), keymap.action(SDLKeycode::Left));
+}
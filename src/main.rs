@@ -6,6 +6,8 @@ TODO: Please rewrite the tokenizer.
 */
 #[allow(dead_code)]
 mod markup;
+mod code_highlighting;
+mod shapes;
 #[allow(dead_code)]
 mod utility;
 pub use self::utility::*;
@@ -17,6 +19,14 @@ use self::color::*;
 mod slide_parser;
 mod slide;
 mod application;
+mod keymap;
+mod settings;
+mod recent_files;
+mod file_watcher;
+mod system_fonts;
+mod text_texture_cache;
+mod glyph_atlas;
+mod text_shaping;
 use self::application::*;
 
 mod application_states;
@@ -25,6 +35,10 @@ mod options_state;
 mod change_page_state;
 mod showing_slide_state;
 mod select_slide_to_load_state;
+mod presenter_state;
+mod overview_state;
+mod blank_screen_state;
+mod resume_prompt_state;
 
 const DEFAULT_WINDOW_WIDTH  : u32 = 1280;
 const DEFAULT_WINDOW_HEIGHT : u32 = 720;
@@ -51,13 +65,13 @@ fn main() {
                                                         &sdl2_ttf_context,
                                                         &sdl2_image_context,
                                                         &video_subsystem);
-    graphics_context.add_font("data/fonts/libre-baskerville/LibreBaskerville-Regular.ttf");
-
     let mut event_pump = sdl2_context.event_pump().unwrap();
 
     use std::env;
     let arguments : Vec<String> = env::args().collect();
     let mut application_state = ApplicationState::new(&arguments);
+    application_state.apply_settings(&mut graphics_context);
+    graphics_context.add_font(&application_state.settings.default_font);
 
     let mut sdl2_timer = sdl2_context.timer().unwrap();
     let mut delta_time = 0;
@@ -70,12 +84,14 @@ fn main() {
         graphics_context.use_viewport_default();
 
         if let ApplicationScreen::Quit(_) = application_state.state {
+            application_state.save_unsaved_recent_files();
             break 'running;
         } else {
             let delta_time = delta_time as f32 / 1000.0;
 
             application_state.handle_event(&mut graphics_context, &mut event_pump, delta_time);
-            application_state.update(delta_time);
+            application_state.update(&mut graphics_context, delta_time);
+            graphics_context.clear_clickable_regions();
             application_state.draw(&mut graphics_context);
         }
 
@@ -1,191 +1,484 @@
 /*
-I know there's a way to do it with just &str and slices, but
-the iteration work! OMG it's so much.
+    Markup inside a line of text:
+
+        *bold*  /italics/  _underlined_  +strikethrough+  [[page|link text]]  `code`
+        {color=#f00 size=40 font="Fira Sans"|just these words}  {@accent|a $span-style}
+
+    These nest and combine, so *_bold and underlined_* works. A backslash in
+    front of a markup character makes it plain text (\*not bold\*). Nothing inside
+    `code` is markup.
+
+    A marker opens at the start of a word and closes at the end of one, so
+    snake_case_names and a + b are left alone. Markers that are never closed
+    are just text.
 */
-#[derive(Debug, PartialEq)]
-// should this just be a struct? They're all the same...
-pub enum Markup {
-    Plain(String),
-    Bold(String),
-    Strikethrough(String),
-    Italics(String),
-    Underlined(String),
+use std::collections::HashMap;
+
+use crate::color::*;
+
+// Any number of these at once, it's a bitset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MarkupStyle(u8);
+
+impl MarkupStyle {
+    pub const PLAIN : MarkupStyle = MarkupStyle(0);
+    pub const BOLD : MarkupStyle = MarkupStyle(1 << 0);
+    pub const ITALICS : MarkupStyle = MarkupStyle(1 << 1);
+    pub const UNDERLINED : MarkupStyle = MarkupStyle(1 << 2);
+    pub const STRIKETHROUGH : MarkupStyle = MarkupStyle(1 << 3);
+    // monospace, on a little background.
+    pub const CODE : MarkupStyle = MarkupStyle(1 << 4);
+
+    pub fn contains(self, other: MarkupStyle) -> bool {
+        self.0 & other.0 == other.0
+    }
 }
 
-impl Markup {
-    // divorce this from sdl2?
-    pub fn get_text_drawing_style(&self) -> sdl2::ttf::FontStyle {
-        use sdl2::ttf::FontStyle;
-        match self {
-            Markup::Bold(_) => { FontStyle::BOLD },
-            Markup::Italics(_) => { FontStyle::ITALIC },
-            _ => { FontStyle::NORMAL },
-        }
+impl std::ops::BitOr for MarkupStyle {
+    type Output = MarkupStyle;
+    fn bitor(self, other: MarkupStyle) -> MarkupStyle {
+        MarkupStyle(self.0 | other.0)
     }
+}
 
-    pub fn get_text_content(&self) -> &str {
-        #[allow(unreachable_patterns)]
-        match self {
-            Markup::Plain(text_content)|
-            Markup::Bold(text_content)|
-            Markup::Strikethrough(text_content)|
-            Markup::Italics(text_content)|
-            Markup::Underlined(text_content) => {
-                &text_content
-            }
-            _ => { "" },
+impl std::ops::BitOrAssign for MarkupStyle {
+    fn bitor_assign(&mut self, other: MarkupStyle) {
+        self.0 |= other.0;
+    }
+}
+
+// What a {...|text} span changes, None is whatever the line has.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpanStyle {
+    pub color: Option<Color>,
+    pub size: Option<u16>,
+    pub font: Option<String>,
+}
+
+impl SpanStyle {
+    // other's settings win.
+    pub fn merged_with(&self, other: &SpanStyle) -> SpanStyle {
+        SpanStyle {
+            color: other.color.or(self.color),
+            size: other.size.or(self.size),
+            font: other.font.clone().or_else(|| self.font.clone()),
         }
     }
 }
 
-// I had to lookup a basic lexer in Rust... Cause holy s**t whatever I was
-// doing was really confusing.
-pub struct MarkupLexer<'a> {
-    iterator: std::iter::Peekable<std::str::Chars<'a>>,
+// color=#f00 size=40 font="Fira Sans" @accent
+// Named styles (@name) are applied where they're written, so later settings override them.
+pub fn parse_span_style(source: &str, named_styles: &HashMap<String, SpanStyle>) -> Result<SpanStyle, &'static str> {
+    fn split_value(source: &str) -> Result<(&str, &str), &'static str> {
+        if let Some(quoted) = source.strip_prefix('"') {
+            let end = quoted.find('"').ok_or("Unclosed string in span.")?;
+            Ok((&quoted[..end], &quoted[end+1..]))
+        } else {
+            let end = source.find(|character: char| character.is_whitespace()).unwrap_or(source.len());
+            Ok((&source[..end], &source[end..]))
+        }
+    }
+
+    let mut span_style = SpanStyle::default();
+    let mut source = source;
+    loop {
+        source = source.trim_start();
+        if source.is_empty() {
+            break;
+        }
+
+        if let Some(named) = source.strip_prefix('@') {
+            let (name, rest) = split_value(named)?;
+            let named_style = named_styles.get(name).ok_or("No span-style by that name.")?;
+            span_style = span_style.merged_with(named_style);
+            source = rest;
+            continue;
+        }
+
+        let equals = source.find('=').ok_or("Expected key=value or @style in span.")?;
+        let key = &source[..equals];
+        let (value, rest) = split_value(&source[equals+1..])?;
+        match key {
+            "color" => {
+                span_style.color = Some(Color::parse_hexadecimal_literal(value).ok_or("Bad color in span, expected #rgb or #rrggbb.")?);
+            },
+            "size" => {
+                span_style.size = Some(value.parse::<u16>().map_err(|_| "Bad size in span.")?);
+            },
+            "font" => {
+                span_style.font = Some(value.to_owned());
+            },
+            _ => { return Err("Unknown setting in span, expected color, size or font."); }
+        }
+        source = rest;
+    }
+
+    Ok(span_style)
 }
 
-pub fn is_whitespace(c: char) -> bool {
-    c == ' ' || c == '\n' || c == '\t' || c == '\r'
+// A run of text that's all drawn the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Markup {
+    pub text: String,
+    pub style: MarkupStyle,
+    // None is whatever color the line is.
+    pub color: Option<Color>,
+    pub size: Option<u16>,
+    pub font: Option<String>,
+    // page id or number.
+    pub link: Option<String>,
 }
 
-/*
-   Real markup has multicharacter patterns, which
-   should still be fairly easy to adopt here...
-*/
-impl<'a> MarkupLexer<'a> {
-    // this is self consuming since the iterator
-    // will be used up. Probably debugging stuff.
-    // It "renders" the text into a string. It removes all markup characters
-    // and returns plain text.
-    pub fn stitch(self) -> String {
-        self.fold(String::new(),
-                  |mut accumulated_string, markup| {
-                      accumulated_string.push_str(&markup.get_text_content());
-                      accumulated_string
-                  })
+impl Markup {
+    pub fn plain(text: &str) -> Markup {
+        Markup::styled(text, MarkupStyle::PLAIN)
     }
 
-    fn is_special_character(c: char) -> bool {
-        match c {
-            '*' | '/' | '_' | '+' => true,
-            _ => false,
+    pub fn styled(text: &str, style: MarkupStyle) -> Markup {
+        Markup {
+            text: text.to_owned(),
+            style,
+            color: None,
+            size: None,
+            font: None,
+            link: None,
         }
     }
 
-    pub fn new(source: &'a str) -> MarkupLexer<'a> {
-        MarkupLexer {
-            iterator: source.chars().peekable()
+    pub fn spanned(text: &str, style: MarkupStyle, span_style: &SpanStyle) -> Markup {
+        Markup {
+            color: span_style.color,
+            size: span_style.size,
+            font: span_style.font.clone(),
+            .. Markup::styled(text, style)
         }
     }
 
-    fn peek_character(&mut self) -> Option<&char> {
-        self.iterator.peek()
+    // Links are always blue and underlined, they can still change size or font.
+    pub fn link(target: &str, text: &str, style: MarkupStyle, span_style: &SpanStyle) -> Markup {
+        Markup {
+            color: Some(COLOR_LINK_BLUE),
+            link: Some(target.to_owned()),
+            .. Markup::spanned(text, style | MarkupStyle::UNDERLINED, span_style)
+        }
     }
 
-    fn next_character(&mut self) -> Option<char> {
-        self.iterator.next()
+    // Only the styles that change the face, lines are drawn by Page::render.
+    pub fn get_text_drawing_style(&self) -> sdl2::ttf::FontStyle {
+        use sdl2::ttf::FontStyle;
+        let mut font_style = FontStyle::NORMAL;
+        if self.style.contains(MarkupStyle::BOLD) {
+            font_style |= FontStyle::BOLD;
+        }
+        if self.style.contains(MarkupStyle::ITALICS) {
+            font_style |= FontStyle::ITALIC;
+        }
+        font_style
+    }
+
+    pub fn get_text_content(&self) -> &str {
+        &self.text
     }
+}
+
+pub fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\n' || c == '\t' || c == '\r'
+}
+
+fn is_marker_character(c: char) -> bool {
+    matches!(c, '*' | '/' | '_' | '+')
+}
+
+// Anything a backslash can make plain.
+fn is_escapable_character(c: char) -> bool {
+    is_marker_character(c) || c == '\\' || c == '[' || c == '{' || c == '}' || c == '`'
+}
+
+fn marker_style(c: char) -> MarkupStyle {
+    match c {
+        '*' => MarkupStyle::BOLD,
+        '/' => MarkupStyle::ITALICS,
+        '_' => MarkupStyle::UNDERLINED,
+        '+' => MarkupStyle::STRIKETHROUGH,
+        _ => MarkupStyle::PLAIN,
+    }
+}
+
+// Backslashes in front of everything that would be read as markup, so the text
+// comes out exactly as written.
+pub fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if is_escapable_character(character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+#[derive(Debug)]
+enum MarkupPiece {
+    Character(char),
+    Marker { character: char, can_open: bool, can_close: bool },
+    // target, text shown.
+    Link(String, String),
+    // {settings| and the text it was written as, in case it's never closed.
+    SpanOpen(String, String),
+    SpanClose,
+    Code(String),
+}
 
-    fn next_words_until_special(&mut self) -> String {
-        let mut sentence : String = String::new();
-        let mut previous_character : Option<char> = None;
+fn markup_pieces(source: &str) -> Vec<MarkupPiece> {
+    let characters : Vec<char> = source.chars().collect();
+    let mut pieces = Vec::with_capacity(characters.len());
+    let mut index = 0;
 
-        while let Some(&character) = self.peek_character() {
-            if MarkupLexer::is_special_character(character) {
-                if let Some(&next_character) = self.peek_character() {
-                    if let Some(previous_character) = previous_character {
-                        if !is_whitespace(next_character) && is_whitespace(previous_character) {
-                            return sentence;
-                        }
+    while index < characters.len() {
+        let character = characters[index];
+        let next_character = characters.get(index + 1).copied();
+
+        match character {
+            // other backslashes are left alone, C:\Users shouldn't lose one.
+            '\\' if next_character.map(is_escapable_character).unwrap_or(false) => {
+                pieces.push(MarkupPiece::Character(next_character.unwrap()));
+                index += 2;
+            },
+            // [[target|text]] or [[target]]
+            '[' if next_character == Some('[') => {
+                let link_end = (index + 2..characters.len().saturating_sub(1))
+                    .find(|end| characters[*end] == ']' && characters[*end + 1] == ']');
+
+                match link_end {
+                    Some(link_end) => {
+                        let contents : String = characters[index + 2..link_end].iter().collect();
+                        let mut parts = contents.splitn(2, '|');
+                        let target = parts.next().unwrap_or("").trim().to_owned();
+                        let text = match parts.next() {
+                            Some(text) => text.to_owned(),
+                            None => target.clone(),
+                        };
+                        pieces.push(MarkupPiece::Link(target, text));
+                        index = link_end + 2;
+                    },
+                    // never closed, so it was never a link.
+                    None => {
+                        pieces.push(MarkupPiece::Character(character));
+                        index += 1;
+                    }
+                }
+            },
+            // {settings|text}, anything else in braces is just text.
+            '{' => {
+                let settings_end = characters[index + 1..].iter()
+                    .position(|character| matches!(character, '|' | '{' | '}' | '\n'))
+                    .map(|end| index + 1 + end)
+                    .filter(|end| characters[*end] == '|');
+
+                match settings_end {
+                    Some(settings_end) => {
+                        let settings : String = characters[index + 1..settings_end].iter().collect();
+                        let written : String = characters[index..=settings_end].iter().collect();
+                        pieces.push(MarkupPiece::SpanOpen(settings, written));
+                        index = settings_end + 1;
+                    },
+                    None => {
+                        pieces.push(MarkupPiece::Character(character));
+                        index += 1;
                     }
                 }
+            },
+            '}' => {
+                pieces.push(MarkupPiece::SpanClose);
+                index += 1;
+            },
+            // everything up to the next backtick, exactly as written.
+            '`' => {
+                let code_end = characters[index + 1..].iter()
+                    .position(|character| *character == '`')
+                    .map(|end| index + 1 + end);
+
+                match code_end {
+                    Some(code_end) => {
+                        pieces.push(MarkupPiece::Code(characters[index + 1..code_end].iter().collect()));
+                        index = code_end + 1;
+                    },
+                    None => {
+                        pieces.push(MarkupPiece::Character(character));
+                        index += 1;
+                    }
+                }
+            },
+            _ if is_marker_character(character) => {
+                let previous_character = if index > 0 { Some(characters[index - 1]) } else { None };
+                // markers hug the word they're around, and don't open or close in the middle of one.
+                let can_open =
+                    next_character.map(|next| !is_whitespace(next)).unwrap_or(false) &&
+                    previous_character.map(|previous| !previous.is_alphanumeric()).unwrap_or(true);
+                let can_close =
+                    previous_character.map(|previous| !is_whitespace(previous)).unwrap_or(false) &&
+                    next_character.map(|next| !next.is_alphanumeric()).unwrap_or(true);
+                pieces.push(MarkupPiece::Marker { character, can_open, can_close });
+                index += 1;
+            },
+            _ => {
+                pieces.push(MarkupPiece::Character(character));
+                index += 1;
             }
-            sentence.push(character);
-            previous_character = Some(character);
-            self.next_character().unwrap();
         }
-        sentence
     }
 
-    fn find_type(identifier: char, text_contents: String) -> Markup {
-        match identifier {
-            '*' => Markup::Bold(text_contents),
-            '+' => Markup::Strikethrough(text_contents),
-            '/' => Markup::Italics(text_contents),
-            '_' => Markup::Underlined(text_contents),
-            _ => Markup::Plain(text_contents),
-        }
-    }
+    pieces
+}
 
-    fn find_match_and_pass(&mut self, to_match: char) -> (String, bool) {
-        let mut sentence : String = String::new();
-        let mut previous_character : Option<char> = None;
+// For the slide compiler, so a bad span is an error with a line number. Slides
+// keep what this returns, so their text isn't parsed again every frame.
+pub fn parse_markup(source: &str, named_styles: &HashMap<String, SpanStyle>) -> Result<Vec<Markup>, &'static str> {
+    let pieces = markup_pieces(source);
+    let mut styles = vec![MarkupStyle::PLAIN; pieces.len()];
+    let mut matched = vec![false; pieces.len()];
 
-        self.next_character();
+    // Spans nest like brackets, each piece gets the settings of every span it's inside.
+    // 0 is no span at all.
+    let mut span_styles = vec![SpanStyle::default()];
+    let mut piece_spans = vec![0; pieces.len()];
+    {
+        let mut open_spans : Vec<(usize, usize)> = Vec::new();
+        for (index, piece) in pieces.iter().enumerate() {
+            match piece {
+                MarkupPiece::SpanOpen(..) => {
+                    open_spans.push((index, 0));
+                },
+                MarkupPiece::SpanClose => {
+                    if let Some((opener_index, _)) = open_spans.pop() {
+                        matched[opener_index] = true;
+                        matched[index] = true;
+                    }
+                },
+                _ => {},
+            }
+        }
 
-        fn is_punctuation(c: char) -> bool {
-            match c {
-                '!'|'?'|'.'|';'|','|'\''|'\"' => { true },
-                _ => { false },
+        // only spans that are closed have to make sense.
+        open_spans.clear();
+        for (index, piece) in pieces.iter().enumerate() {
+            match piece {
+                MarkupPiece::SpanOpen(settings, _) if matched[index] => {
+                    let outer = open_spans.last().map(|(_, span)| *span).unwrap_or(0);
+                    let span_style = parse_span_style(settings, named_styles)?;
+                    span_styles.push(span_styles[outer].merged_with(&span_style));
+                    open_spans.push((index, span_styles.len() - 1));
+                },
+                MarkupPiece::SpanClose if matched[index] => {
+                    open_spans.pop();
+                },
+                _ => {
+                    piece_spans[index] = open_spans.last().map(|(_, span)| *span).unwrap_or(0);
+                },
             }
         }
+    }
 
-        while let Some(character) = self.next_character() {
-            if character == to_match {
-                let good_match =
-                    previous_character.and_then(
-                        |previous_character| {
-                            if !is_whitespace(previous_character) && previous_character != character {
-                                let result = self.peek_character()
-                                    .and_then(|c| Some(is_punctuation(*c) ||
-                                                       is_whitespace(*c)));
-                                Some(result.unwrap_or(true))
-                            } else {
-                                None
-                            }
-                        }).unwrap_or(false);
-
-                if good_match {
-                    return (sentence, good_match);
+    // Markers close the most recent open one of the same kind. Anything opened
+    // after that and still open can't be closed any more, so it stays text.
+    let mut open_markers : Vec<(usize, char)> = Vec::new();
+    for (index, piece) in pieces.iter().enumerate() {
+        if let MarkupPiece::Marker { character, can_open, can_close } = *piece {
+            if can_close {
+                let opener = open_markers.iter()
+                    .rposition(|(opener_index, opener_character)| *opener_character == character && opener_index + 1 < index);
+
+                if let Some(opener) = opener {
+                    let opener_index = open_markers[opener].0;
+                    open_markers.truncate(opener);
+                    matched[opener_index] = true;
+                    matched[index] = true;
+                    for style in &mut styles[opener_index + 1..index] {
+                        *style |= marker_style(character);
+                    }
+                    continue;
                 }
             }
-            sentence.push(character);
-            previous_character = Some(character);
+
+            if can_open {
+                open_markers.push((index, character));
+            }
         }
-        (sentence, false)
     }
 
-    fn next_markup_item(&mut self) -> Option<Markup> {
-        fn string_prepend(input: &String, c: char) -> String {
-            let mut result = String::new();
-            result.push(c);
-            result.push_str(input);
-            result
-        }
+    let mut markups : Vec<Markup> = Vec::new();
+    for (index, piece) in pieces.into_iter().enumerate() {
+        let style = styles[index];
+        let span_style = &span_styles[piece_spans[index]];
+        let text = match piece {
+            MarkupPiece::Link(target, text) => {
+                markups.push(Markup::link(&target, &text, style, span_style));
+                continue;
+            },
+            MarkupPiece::Code(code) => {
+                markups.push(Markup::spanned(&code, style | MarkupStyle::CODE, span_style));
+                continue;
+            },
+            MarkupPiece::Marker { .. } | MarkupPiece::SpanOpen(..) | MarkupPiece::SpanClose if matched[index] => continue,
+            MarkupPiece::Marker { character, .. } | MarkupPiece::Character(character) => character.to_string(),
+            MarkupPiece::SpanOpen(_, written) => written,
+            MarkupPiece::SpanClose => String::from("}"),
+        };
 
-        if let Some(&character) = self.peek_character() {
-            if MarkupLexer::is_special_character(character) {
-                let (text_within_boundaries, was_good_match) = self.find_match_and_pass(character);
-                Some(
-                    if was_good_match {
-                        MarkupLexer::find_type(character, text_within_boundaries)
-                    } else {
-                        Markup::Plain(string_prepend(&text_within_boundaries, character))
-                    }
-                )
-            } else {
-                Some(Markup::Plain(self.next_words_until_special()))
+        let next = Markup::spanned(&text, style, span_style);
+        match markups.last_mut() {
+            Some(last) if last.link.is_none() && last.style == next.style &&
+                last.color == next.color && last.size == next.size && last.font == next.font => {
+                last.text.push_str(&text);
+            },
+            _ => {
+                markups.push(next);
             }
-        } else {
-            None
         }
     }
+
+    Ok(markups)
 }
 
-impl<'a> Iterator for MarkupLexer<'a> {
+// I had to lookup a basic lexer in Rust... Cause holy s**t whatever I was
+// doing was really confusing.
+// It's parsed all at once now, since a closing marker can come much later.
+pub struct MarkupLexer {
+    markups: std::vec::IntoIter<Markup>,
+}
+
+impl MarkupLexer {
+    pub fn new(source: &str) -> MarkupLexer {
+        MarkupLexer::with_span_styles(source, &HashMap::new())
+    }
+
+    // A span that can't be read is left as it was written, the slide compiler
+    // already complained about it with parse_markup.
+    pub fn with_span_styles(source: &str, named_styles: &HashMap<String, SpanStyle>) -> MarkupLexer {
+        let markups = parse_markup(source, named_styles)
+            .unwrap_or_else(|_| vec![Markup::plain(source)]);
+        MarkupLexer {
+            markups: markups.into_iter()
+        }
+    }
+
+    // this is self consuming since the iterator
+    // will be used up. Probably debugging stuff.
+    // It "renders" the text into a string. It removes all markup characters
+    // and returns plain text.
+    pub fn stitch(self) -> String {
+        self.fold(String::new(),
+                  |mut accumulated_string, markup| {
+                      accumulated_string.push_str(markup.get_text_content());
+                      accumulated_string
+                  })
+    }
+}
+
+impl Iterator for MarkupLexer {
     type Item = Markup;
     fn next(&mut self) -> Option<Self::Item> {
-        self.next_markup_item()
+        self.markups.next()
     }
 }
 
@@ -199,36 +492,131 @@ to use .stitch().
 // but these are one off so whatever.
 fn very_simple_case_a() {
     let markup_lex = MarkupLexer::new("_t t_");
-    assert_eq!(vec![Markup::Underlined(String::from("t t"))],
+    assert_eq!(vec![Markup::styled("t t", MarkupStyle::UNDERLINED)],
                markup_lex.collect::<Vec<Markup>>());
 }
 #[test]
 fn very_simple_case_b() {
     let markup_lex = MarkupLexer::new("+t t+");
-    assert_eq!(vec![Markup::Strikethrough(String::from("t t"))],
+    assert_eq!(vec![Markup::styled("t t", MarkupStyle::STRIKETHROUGH)],
                markup_lex.collect::<Vec<Markup>>());
 }
 #[test]
 fn simpler_case_a() {
     let markup_lex = MarkupLexer::new("a + b");
-    assert_eq!(vec![Markup::Plain(String::from("a ")),
-                    Markup::Plain(String::from("+ b"))],
+    assert_eq!(vec![Markup::plain("a + b")],
                markup_lex.collect::<Vec<Markup>>());
 }
 #[test]
 fn simpler_case_b() {
+    // the first one is never closed, the second one is.
     let markup_lex = MarkupLexer::new("_sad _t t_");
-    assert_eq!(vec![Markup::Underlined(String::from("sad _t t"))],
+    assert_eq!(vec![Markup::plain("_sad "),
+                    Markup::styled("t t", MarkupStyle::UNDERLINED)],
                markup_lex.collect::<Vec<Markup>>());
 }
 #[test]
 fn test_output() {
     let markup_lex = MarkupLexer::new("This is a *thing* Cool_right_ _sad _t t_ a + b!");
-    assert_eq!(vec![Markup::Plain(String::from("This is a ")),
-                    Markup::Bold(String::from("thing")),
-                    Markup::Plain(String::from(" Cool_right_ ")),
-                    Markup::Underlined(String::from("sad _t t")),
-                    Markup::Plain(String::from(" a ")),
-                    Markup::Plain(String::from("+ b!"))],
+    assert_eq!(vec![Markup::plain("This is a "),
+                    Markup::styled("thing", MarkupStyle::BOLD),
+                    Markup::plain(" Cool_right_ _sad "),
+                    Markup::styled("t t", MarkupStyle::UNDERLINED),
+                    Markup::plain(" a + b!")],
+               markup_lex.collect::<Vec<Markup>>());
+}
+#[test]
+fn nested_case() {
+    let markup_lex = MarkupLexer::new("*_bold underline_* and /it +both+/");
+    assert_eq!(vec![Markup::styled("bold underline", MarkupStyle::BOLD | MarkupStyle::UNDERLINED),
+                    Markup::plain(" and "),
+                    Markup::styled("it ", MarkupStyle::ITALICS),
+                    Markup::styled("both", MarkupStyle::ITALICS | MarkupStyle::STRIKETHROUGH)],
+               markup_lex.collect::<Vec<Markup>>());
+}
+#[test]
+fn escaped_case() {
+    let markup_lex = MarkupLexer::new("\\*not bold\\* but *bold*, C:\\Users");
+    assert_eq!(vec![Markup::plain("*not bold* but "),
+                    Markup::styled("bold", MarkupStyle::BOLD),
+                    Markup::plain(", C:\\Users")],
+               markup_lex.collect::<Vec<Markup>>());
+}
+#[test]
+fn code_case() {
+    let markup_lex = MarkupLexer::new("call *`frob(*a, b_c)`* or `unclosed");
+    assert_eq!(vec![Markup::plain("call "),
+                    Markup::styled("frob(*a, b_c)", MarkupStyle::BOLD | MarkupStyle::CODE),
+                    Markup::plain(" or `unclosed")],
+               markup_lex.collect::<Vec<Markup>>());
+}
+#[test]
+fn link_case() {
+    let markup_lex = MarkupLexer::new("see [[architecture|the details]] or *[[14]]*");
+    assert_eq!(vec![Markup::plain("see "),
+                    Markup::link("architecture", "the details", MarkupStyle::PLAIN, &SpanStyle::default()),
+                    Markup::plain(" or "),
+                    Markup::link("14", "14", MarkupStyle::BOLD, &SpanStyle::default())],
+               markup_lex.collect::<Vec<Markup>>());
+}
+#[test]
+fn span_case() {
+    let mut named_styles = HashMap::new();
+    named_styles.insert(String::from("accent"), SpanStyle { color: Some(COLOR_RIPE_LEMON), size: None, font: Some(String::from("mono")) });
+    let red = SpanStyle { color: Some(Color::new(255, 0, 0, 255)), size: Some(40), font: None };
+    let markup_lex = MarkupLexer::with_span_styles("a {color=#f00 size=40|*big* warning} {@accent size=20|b} {not|a span", &named_styles);
+    assert_eq!(vec![Markup::plain("a "),
+                    Markup::spanned("big", MarkupStyle::BOLD, &red),
+                    Markup::spanned(" warning", MarkupStyle::PLAIN, &red),
+                    Markup::plain(" "),
+                    Markup::spanned("b", MarkupStyle::PLAIN, &SpanStyle { size: Some(20), .. named_styles["accent"].clone() }),
+                    Markup::plain(" {not|a span")],
                markup_lex.collect::<Vec<Markup>>());
 }
+#[test]
+fn nested_span_case() {
+    let markup_lex = MarkupLexer::new("{size=10|a {color=#00ff00|b} c} }");
+    let small = SpanStyle { size: Some(10), .. SpanStyle::default() };
+    assert_eq!(vec![Markup::spanned("a ", MarkupStyle::PLAIN, &small),
+                    Markup::spanned("b", MarkupStyle::PLAIN, &SpanStyle { color: Some(Color::new(0, 255, 0, 255)), .. small.clone() }),
+                    Markup::spanned(" c", MarkupStyle::PLAIN, &small),
+                    Markup::plain(" }")],
+               markup_lex.collect::<Vec<Markup>>());
+}
+#[test]
+fn bad_span_case() {
+    assert!(parse_markup("{colour=#f00|a}", &HashMap::new()).is_err());
+    assert!(parse_markup("{color=red|a}", &HashMap::new()).is_err());
+    assert!(parse_markup("{@nothing|a}", &HashMap::new()).is_err());
+    let fonts : Vec<Option<String>> = parse_markup("{font=\"Fira Sans\"|a} {size=3|b}", &HashMap::new()).unwrap()
+        .into_iter()
+        .map(|markup| markup.font)
+        .collect();
+    assert_eq!(vec![Some(String::from("Fira Sans")), None, None], fonts);
+    assert_eq!(String::from("{color=red|a}"), MarkupLexer::new("{color=red|a}").stitch());
+}
+#[test]
+fn unclosed_link_case() {
+    let markup_lex = MarkupLexer::new("a [[b");
+    assert_eq!(String::from("a [[b"), markup_lex.stitch());
+}
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn stitch_round_trips_escaped_text(text in "\\PC*") {
+        proptest::prop_assert_eq!(&text, &MarkupLexer::new(&escape_markup(&text)).stitch());
+    }
+
+    // words, each one either plain or wrapped in a marker.
+    #[test]
+    fn stitch_drops_markers_around_words(words in proptest::collection::vec(("[a-z]{1,6}( [a-z]{1,6}){0,2}", proptest::sample::select(vec!["", "*", "/", "_", "+"])), 0..8)) {
+        let source = words.iter().map(|(word, marker)| format!("{}{}{}", marker, word, marker)).collect::<Vec<_>>().join(" ");
+        let text = words.iter().map(|(word, _)| word.as_str()).collect::<Vec<_>>().join(" ");
+        let markups : Vec<Markup> = MarkupLexer::new(&source).collect();
+        for (word, marker) in words.iter().filter(|(_, marker)| !marker.is_empty()) {
+            let style = marker_style(marker.chars().next().unwrap());
+            proptest::prop_assert!(markups.iter().any(|markup| &markup.text == word && markup.style == style));
+        }
+        proptest::prop_assert_eq!(text, MarkupLexer::new(&source).stitch());
+    }
+}
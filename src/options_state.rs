@@ -15,29 +15,35 @@ impl ApplicationScreenState for OptionsState {
                 SDLEvent::Quit {..} => {
                     app.state = ApplicationScreen::Quit(QuitState);
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::F), ..} => {
-                    graphics_context.toggle_fullscreen();
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Return), .. } =>  {
-                    let resolution_list = graphics_context.get_avaliable_resolutions();
-                    if let Some(resolution_pair) = resolution_list.get(app.currently_selected_resolution) {
-                        graphics_context.set_resolution((resolution_pair.0 as u32, resolution_pair.1 as u32));
-                    }
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Up), .. } => {
-                    if app.currently_selected_resolution > 0 {
-                        app.currently_selected_resolution -= 1;
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } => {
+                    match app.keymap.action(keycode) {
+                        Some(InputAction::ToggleFullscreen) => {
+                            app.toggle_fullscreen(graphics_context);
+                        },
+                        Some(InputAction::Accept) => {
+                            let resolution_list = graphics_context.get_avaliable_resolutions();
+                            if let Some(resolution_pair) = resolution_list.get(app.currently_selected_resolution) {
+                                app.set_window_resolution(graphics_context, (resolution_pair.0 as u32, resolution_pair.1 as u32));
+                            }
+                        },
+                        Some(InputAction::SelectionUp) if app.currently_selected_resolution > 0 => {
+                            app.currently_selected_resolution -= 1;
+                        },
+                        Some(InputAction::SelectionDown) => {
+                            app.currently_selected_resolution += 1;
+                        },
+                        Some(InputAction::Cancel) | Some(InputAction::Options) => {
+                            app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                        },
+                        Some(InputAction::OpenBrowser) => {
+                            app.state = ApplicationScreen::SelectSlideToLoad(SelectSlideToLoadState);
+                        },
+                        Some(InputAction::Quit) => {
+                            app.state = ApplicationScreen::Quit(QuitState);
+                        },
+                        _ => {}
                     }
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Down), .. } => {
-                    app.currently_selected_resolution += 1;
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::O), .. } => {
-                    app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::L), .. } => {
-                    app.state = ApplicationScreen::SelectSlideToLoad(SelectSlideToLoadState);
-                },
                 _ => {}
             }
         }
@@ -46,7 +52,7 @@ impl ApplicationScreenState for OptionsState {
     fn draw(&self,
             app: &ApplicationState,
             graphics_context: &mut SDL2GraphicsContext) {
-        let default_font = graphics_context.add_font("data/fonts/libre-baskerville/LibreBaskerville-Regular.ttf");
+        let default_font = graphics_context.add_font(&app.settings.default_font);
         graphics_context.logical_resolution = VirtualResolution::Display;
         graphics_context.clear_color(Color::new(10, 10, 16, 255));
         let heading_font_size = graphics_context.font_size_percent(0.08);
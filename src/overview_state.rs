@@ -0,0 +1,223 @@
+pub use crate::application_states::*;
+
+const OVERVIEW_COLUMNS : usize = 4;
+const OVERVIEW_MARGIN_PERCENT : f32 = 0.02;
+const OVERVIEW_LABEL_PERCENT : f32 = 0.03;
+
+// Shared between drawing and mouse picking so they can't disagree.
+// Everything is in the slide's virtual resolution.
+struct OverviewLayout {
+    margin: f32,
+    cell_width: f32,
+    cell_height: f32,
+    thumbnail_scale: f32,
+    first_visible_row: usize,
+    visible_rows: usize,
+}
+
+impl OverviewLayout {
+    fn new(resolution: (u32, u32), selected_page: usize) -> OverviewLayout {
+        let (width, height) = (resolution.0 as f32, resolution.1 as f32);
+        let margin = height * OVERVIEW_MARGIN_PERCENT;
+        let cell_width = width / OVERVIEW_COLUMNS as f32;
+        let thumbnail_scale = (cell_width - margin * 2.0) / width;
+        let cell_height = height * thumbnail_scale + margin * 2.0 + height * OVERVIEW_LABEL_PERCENT;
+        let visible_rows = ((height / cell_height) as usize).max(1);
+
+        // scroll just enough to keep the selection on screen.
+        let selected_row = selected_page / OVERVIEW_COLUMNS;
+        let first_visible_row = (selected_row + 1).saturating_sub(visible_rows);
+
+        OverviewLayout {
+            margin,
+            cell_width,
+            cell_height,
+            thumbnail_scale,
+            first_visible_row,
+            visible_rows,
+        }
+    }
+
+    fn first_visible_page(&self) -> usize {
+        self.first_visible_row * OVERVIEW_COLUMNS
+    }
+
+    fn last_visible_page(&self) -> usize {
+        (self.first_visible_row + self.visible_rows) * OVERVIEW_COLUMNS
+    }
+
+    // top left of the thumbnail.
+    fn page_position(&self, page: usize) -> (f32, f32) {
+        let row = (page / OVERVIEW_COLUMNS) as f32 - self.first_visible_row as f32;
+        let column = (page % OVERVIEW_COLUMNS) as f32;
+        (column * self.cell_width + self.margin,
+         row * self.cell_height + self.margin)
+    }
+
+    fn page_at(&self, x: f32, y: f32) -> Option<usize> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+
+        let column = (x / self.cell_width) as usize;
+        let row = (y / self.cell_height) as usize;
+        if column < OVERVIEW_COLUMNS && row < self.visible_rows {
+            Some((row + self.first_visible_row) * OVERVIEW_COLUMNS + column)
+        } else {
+            None
+        }
+    }
+}
+
+fn jump_to_selected_page(app: &mut ApplicationState) {
+    if let Some(slideshow) = &mut app.slideshow {
+        slideshow.set_current_page(app.currently_selected_overview_page as isize);
+    }
+    app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+}
+
+impl ApplicationScreenState for OverviewState {
+    fn handle_event(&self,
+                    app: &mut ApplicationState,
+                    graphics_context: &mut SDL2GraphicsContext,
+                    event_pump: &mut sdl2::EventPump,
+                    _delta_time: f32) {
+        let (resolution, page_count) = match &app.slideshow {
+            Some(slideshow) => (slideshow.resolution(), slideshow.len()),
+            None => { return; }
+        };
+        graphics_context.logical_resolution = VirtualResolution::Virtual(resolution.0, resolution.1);
+
+        let last_page = page_count.max(1) - 1;
+        app.currently_selected_overview_page = clamp(app.currently_selected_overview_page, 0, last_page);
+
+        for event in event_pump.poll_iter() {
+            let selected_page = app.currently_selected_overview_page;
+            match event {
+                SDLEvent::Quit {..} => {
+                    app.state = ApplicationScreen::Quit(QuitState);
+                },
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } => {
+                    match app.keymap.action(keycode) {
+                        Some(InputAction::Cancel) | Some(InputAction::Overview) => {
+                            app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                        },
+                        Some(InputAction::ToggleFullscreen) => {
+                            app.toggle_fullscreen(graphics_context);
+                        },
+                        Some(InputAction::ToggleDebugOverlay) => {
+                            app.show_debug_overlay = !app.show_debug_overlay;
+                        },
+                        Some(InputAction::Accept) => {
+                            jump_to_selected_page(app);
+                        },
+                        Some(InputAction::NextPage) => {
+                            app.currently_selected_overview_page = (selected_page + 1).min(last_page);
+                        },
+                        Some(InputAction::PreviousPage) => {
+                            app.currently_selected_overview_page = selected_page.saturating_sub(1);
+                        },
+                        Some(InputAction::SelectionDown) => {
+                            app.currently_selected_overview_page = (selected_page + OVERVIEW_COLUMNS).min(last_page);
+                        },
+                        Some(InputAction::SelectionUp) => {
+                            app.currently_selected_overview_page = selected_page.saturating_sub(OVERVIEW_COLUMNS);
+                        },
+                        Some(InputAction::FirstPage) => {
+                            app.currently_selected_overview_page = 0;
+                        },
+                        Some(InputAction::LastPage) => {
+                            app.currently_selected_overview_page = last_page;
+                        },
+                        Some(InputAction::Quit) => {
+                            app.state = ApplicationScreen::Quit(QuitState);
+                        },
+                        _ => {}
+                    }
+                },
+                SDLEvent::MouseWheel { y, .. } => {
+                    if y < 0 {
+                        app.currently_selected_overview_page = (selected_page + OVERVIEW_COLUMNS).min(last_page);
+                    } else if y > 0 {
+                        app.currently_selected_overview_page = selected_page.saturating_sub(OVERVIEW_COLUMNS);
+                    }
+                },
+                SDLEvent::MouseMotion { x, y, .. } => {
+                    let (x, y) = graphics_context.window_xy_pair_to_logical(x, y);
+                    if let Some(page) = OverviewLayout::new(resolution, selected_page).page_at(x, y) {
+                        if page < page_count {
+                            app.currently_selected_overview_page = page;
+                        }
+                    }
+                },
+                SDLEvent::MouseButtonDown { mouse_btn: sdl2::mouse::MouseButton::Left, x, y, .. } => {
+                    let (x, y) = graphics_context.window_xy_pair_to_logical(x, y);
+                    if let Some(page) = OverviewLayout::new(resolution, selected_page).page_at(x, y) {
+                        if page < page_count {
+                            app.currently_selected_overview_page = page;
+                            jump_to_selected_page(app);
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&self,
+            app: &ApplicationState,
+            graphics_context: &mut SDL2GraphicsContext) {
+        let default_font = graphics_context.add_font(&app.settings.default_font);
+        graphics_context.clear_color(Color::new(10, 10, 16, 255));
+
+        if let Some(slideshow) = &app.slideshow {
+            let resolution = slideshow.resolution();
+            let layout = OverviewLayout::new(resolution, app.currently_selected_overview_page);
+            let thumbnail_width = resolution.0 as f32 * layout.thumbnail_scale;
+            let thumbnail_height = resolution.1 as f32 * layout.thumbnail_scale;
+            let label_font_size = (resolution.1 as f32 * OVERVIEW_LABEL_PERCENT) as u16;
+
+            for page in layout.first_visible_page()..layout.last_visible_page().min(slideshow.len()) {
+                let (x, y) = layout.page_position(page);
+
+                graphics_context.camera = Camera::default();
+                graphics_context.logical_resolution = VirtualResolution::Virtual(resolution.0, resolution.1);
+                graphics_context.use_viewport_letterbox();
+                if page == app.currently_selected_overview_page {
+                    let border = layout.margin / 2.0;
+                    graphics_context.render_filled_rectangle(x - border, y - border,
+                                                             thumbnail_width + border * 2.0,
+                                                             thumbnail_height + border * 2.0,
+                                                             COLOR_RIPE_LEMON);
+                }
+                graphics_context.render_text(default_font,
+                                             x, y + thumbnail_height + layout.margin / 2.0,
+                                             &format!("{}", page + 1),
+                                             label_font_size,
+                                             if page == app.currently_selected_overview_page {
+                                                 COLOR_RIPE_LEMON
+                                             } else {
+                                                 COLOR_WHITE
+                                             },
+                                             sdl2::ttf::FontStyle::NORMAL);
+
+                // pages are free to draw outside of themselves, which would spill into
+                // their neighbours here.
+                graphics_context.set_clip_rectangle(Some((x, y, thumbnail_width, thumbnail_height)));
+                graphics_context.camera = Camera { x, y, scale: layout.thumbnail_scale };
+                slideshow.try_to_draw_page(graphics_context, default_font, page);
+                graphics_context.set_clip_rectangle(None);
+            }
+
+            graphics_context.camera = Camera::default();
+        }
+    }
+
+    fn update(&self,
+              app: &mut ApplicationState,
+              _delta_time: f32) {
+        if app.slideshow.is_none() {
+            app.state = ApplicationScreen::InvalidOrNoSlide(InvalidOrNoSlideState);
+        }
+    }
+}
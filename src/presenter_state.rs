@@ -0,0 +1,159 @@
+pub use crate::application_states::*;
+
+// Everything here is laid out as a fraction of the slide's own virtual resolution,
+// so the presenter screen letterboxes the same way the slides do.
+const CURRENT_PAGE_SCALE : f32 = 0.60;
+const NEXT_PAGE_SCALE : f32 = 0.33;
+const MARGIN_PERCENT : f32 = 0.02;
+
+impl ApplicationScreenState for PresenterState {
+    fn handle_event(&self,
+                    app: &mut ApplicationState,
+                    graphics_context: &mut SDL2GraphicsContext,
+                    event_pump: &mut sdl2::EventPump,
+                    _delta_time: f32) {
+        for event in event_pump.poll_iter() {
+            match event {
+                SDLEvent::Quit {..} => {
+                    app.state = ApplicationScreen::Quit(QuitState);
+                },
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } => {
+                    match app.keymap.action(keycode) {
+                        Some(InputAction::Cancel) | Some(InputAction::Presenter) => {
+                            app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                        },
+                        Some(InputAction::ToggleFullscreen) => {
+                            app.toggle_fullscreen(graphics_context);
+                        },
+                        Some(InputAction::ToggleDebugOverlay) => {
+                            app.show_debug_overlay = !app.show_debug_overlay;
+                        },
+                        Some(InputAction::ResetTimer) => {
+                            app.presentation_elapsed_time = Some(0.0);
+                        },
+                        // No transitions here, the presenter wants to see where they are immediately.
+                        Some(InputAction::NextPage) => {
+                            app.start_presentation_timer();
+                            if let Some(slideshow) = &mut app.slideshow {
+                                if !slideshow.next_step() {
+                                    slideshow.next_page();
+                                }
+                            }
+                        },
+                        Some(InputAction::PreviousPage) => {
+                            if let Some(slideshow) = &mut app.slideshow {
+                                if !slideshow.previous_step() {
+                                    slideshow.previous_page();
+                                }
+                            }
+                        },
+                        Some(InputAction::Quit) => {
+                            app.state = ApplicationScreen::Quit(QuitState);
+                        },
+                        _ => {}
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&self,
+            app: &ApplicationState,
+            graphics_context: &mut SDL2GraphicsContext) {
+        let default_font = graphics_context.add_font(&app.settings.default_font);
+        graphics_context.clear_color(Color::new(10, 10, 16, 255));
+
+        if let Some(slideshow) = &app.slideshow {
+            let (width, height) = slideshow.resolution();
+            let (width, height) = (width as f32, height as f32);
+            let margin = height * MARGIN_PERCENT;
+            let current_page = slideshow.current_page() as usize;
+
+            if slideshow.get(current_page).is_some() {
+                graphics_context.camera = Camera { x: margin, y: margin, scale: CURRENT_PAGE_SCALE };
+                slideshow.try_to_draw_page(graphics_context, default_font, current_page);
+            }
+
+            let next_page_x = margin * 2.0 + width * CURRENT_PAGE_SCALE;
+            if slideshow.get(current_page + 1).is_some() {
+                graphics_context.camera = Camera { x: next_page_x, y: margin, scale: NEXT_PAGE_SCALE };
+                slideshow.try_to_draw_page(graphics_context, default_font, current_page + 1);
+            }
+
+            graphics_context.camera = Camera::default();
+            graphics_context.logical_resolution = VirtualResolution::Virtual(width as u32, height as u32);
+            graphics_context.use_viewport_letterbox();
+
+            if slideshow.get(current_page + 1).is_none() {
+                graphics_context.render_filled_rectangle(next_page_x, margin,
+                                                         width * NEXT_PAGE_SCALE,
+                                                         height * NEXT_PAGE_SCALE,
+                                                         Color::new(5, 5, 8, 255));
+                graphics_context.render_text(default_font,
+                                             next_page_x + margin, margin * 2.0,
+                                             "End of slideshow",
+                                             graphics_context.font_size_percent(0.035),
+                                             Color::new(128, 128, 128, 255),
+                                             sdl2::ttf::FontStyle::NORMAL);
+            }
+
+            let mut draw_cursor_y = margin * 2.0 + height * NEXT_PAGE_SCALE;
+            {
+                let font_size = graphics_context.font_size_percent(0.035);
+                let page_string = format!("Page {} / {}", current_page + 1, slideshow.len());
+                graphics_context.render_text(default_font,
+                                             next_page_x, draw_cursor_y,
+                                             &page_string,
+                                             font_size,
+                                             Color::new(128, 128, 128, 255),
+                                             sdl2::ttf::FontStyle::NORMAL);
+                draw_cursor_y += font_size as f32 * 1.5;
+            }
+            {
+                let font_size = graphics_context.font_size_percent(0.08);
+                graphics_context.render_text(default_font,
+                                             next_page_x, draw_cursor_y,
+                                             &format_elapsed_time(app.presentation_elapsed_time.unwrap_or(0.0)),
+                                             font_size,
+                                             COLOR_RIPE_LEMON,
+                                             sdl2::ttf::FontStyle::NORMAL);
+                draw_cursor_y += font_size as f32 * 1.2;
+            }
+            {
+                let (hours, minutes, _) = wall_clock_time();
+                graphics_context.render_text(default_font,
+                                             next_page_x, draw_cursor_y,
+                                             &format!("{:02}:{:02}", hours, minutes),
+                                             graphics_context.font_size_percent(0.05),
+                                             COLOR_WHITE,
+                                             sdl2::ttf::FontStyle::NORMAL);
+            }
+
+            let mut draw_cursor_y = margin * 2.0 + height * CURRENT_PAGE_SCALE;
+            if let Some(page) = slideshow.get(current_page) {
+                let font_size = graphics_context.font_size_percent(0.035);
+                for line in &page.notes {
+                    // empty lines can't be rendered, but they should still take space.
+                    if !line.is_empty() {
+                        graphics_context.render_text(default_font,
+                                                     margin, draw_cursor_y,
+                                                     line,
+                                                     font_size,
+                                                     COLOR_WHITE,
+                                                     sdl2::ttf::FontStyle::NORMAL);
+                    }
+                    draw_cursor_y += font_size as f32 * 1.2;
+                }
+            }
+        }
+    }
+
+    fn update(&self,
+              app: &mut ApplicationState,
+              _delta_time: f32) {
+        if app.slideshow.is_none() {
+            app.state = ApplicationScreen::InvalidOrNoSlide(InvalidOrNoSlideState);
+        }
+    }
+}
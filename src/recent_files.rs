@@ -0,0 +1,162 @@
+/*
+    Recently opened slideshows, and the page we were last on in each of them.
+    Kept in <config directory>/slideshow/recent.conf, one per line, as
+
+        <page> <path>
+
+    most recent first.
+*/
+use std::path::{Path, PathBuf};
+
+pub const RECENT_FILES_FILE_NAME : &str = "recent.conf";
+// one key each on the no slide screen.
+pub const MAX_RECENT_FILES : usize = 9;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    pub last_page: isize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RecentFiles {
+    files: Vec<RecentFile>,
+}
+
+// The same deck opened through different relative paths should only show up once.
+fn canonical_path(file_name: &str) -> PathBuf {
+    std::fs::canonicalize(file_name).unwrap_or_else(|_| PathBuf::from(file_name))
+}
+
+impl RecentFiles {
+    pub fn new_from_source(source: &str) -> RecentFiles {
+        let mut recent_files = RecentFiles::default();
+
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, ' ');
+            let last_page = parts.next().and_then(|page| page.parse::<isize>().ok());
+            let path = parts.next().map(|path| path.trim());
+
+            if let (Some(last_page), Some(path)) = (last_page, path) {
+                if recent_files.files.len() < MAX_RECENT_FILES {
+                    recent_files.files.push(RecentFile { path: PathBuf::from(path), last_page });
+                }
+            }
+        }
+
+        recent_files
+    }
+
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+        for file in &self.files {
+            if let Some(path) = file.path.to_str() {
+                source.push_str(&format!("{} {}\n", file.last_page, path));
+            }
+        }
+        source
+    }
+
+    pub fn files(&self) -> &[RecentFile] {
+        &self.files
+    }
+
+    pub fn get(&self, index: usize) -> Option<&RecentFile> {
+        self.files.get(index)
+    }
+
+    pub fn find(&self, file_name: &str) -> Option<&RecentFile> {
+        let path = canonical_path(file_name);
+        self.files.iter().find(|file| file.path == path)
+    }
+
+    // Moves it to the front, keeping the page we were on.
+    pub fn add(&mut self, file_name: &str) {
+        let path = canonical_path(file_name);
+        let last_page = match self.files.iter().position(|file| file.path == path) {
+            Some(index) => self.files.remove(index).last_page,
+            None => 0,
+        };
+
+        self.files.insert(0, RecentFile { path, last_page });
+        self.files.truncate(MAX_RECENT_FILES);
+    }
+
+    // returns whether anything changed, so we don't write the file every frame.
+    pub fn remember_page(&mut self, file_name: &str, page: isize) -> bool {
+        let path = canonical_path(file_name);
+        match self.files.iter_mut().find(|file| file.path == path) {
+            Some(file) if file.last_page != page => {
+                file.last_page = page;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn file_path() -> Option<PathBuf> {
+        crate::utility::user_config_directory().map(|directory| directory.join(RECENT_FILES_FILE_NAME))
+    }
+
+    pub fn load() -> RecentFiles {
+        use crate::utility::*;
+        match RecentFiles::file_path().as_ref().and_then(|path| path.to_str()).map(load_file) {
+            Some(Ok(source)) => RecentFiles::new_from_source(&source),
+            _ => RecentFiles::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = RecentFiles::file_path() {
+            if let Some(directory) = path.parent() {
+                if std::fs::create_dir_all(directory).is_err() {
+                    return;
+                }
+            }
+
+            if let Err(error) = std::fs::write(&path, self.to_source()) {
+                println!("warning: could not save {}: {}", path.display(), error);
+            }
+        }
+    }
+}
+
+impl RecentFile {
+    pub fn display_name(&self) -> String {
+        self.path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.to_string_lossy().into_owned())
+    }
+
+    pub fn exists(&self) -> bool {
+        Path::new(&self.path).is_file()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn recent_files_round_trip() {
+    let recent_files = RecentFiles::new_from_source("3 /talks/rust deck.slide\n0 /talks/other.slide\nnonsense\n");
+    assert_eq!(2, recent_files.files().len());
+    assert_eq!(PathBuf::from("/talks/rust deck.slide"), recent_files.files()[0].path);
+    assert_eq!(3, recent_files.files()[0].last_page);
+    assert_eq!(recent_files, RecentFiles::new_from_source(&recent_files.to_source()));
+}
+#[test]
+fn recent_files_most_recent_first() {
+    let mut recent_files = RecentFiles::new_from_source("3 /a.slide\n5 /b.slide\n");
+    recent_files.add("/b.slide");
+    assert_eq!(PathBuf::from("/b.slide"), recent_files.files()[0].path);
+    assert_eq!(5, recent_files.files()[0].last_page);
+    assert!(recent_files.remember_page("/a.slide", 4));
+    assert!(!recent_files.remember_page("/a.slide", 4));
+    for index in 0..MAX_RECENT_FILES {
+        recent_files.add(&format!("/{}.slide", index));
+    }
+    assert_eq!(MAX_RECENT_FILES, recent_files.files().len());
+}
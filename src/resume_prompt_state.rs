@@ -0,0 +1,76 @@
+pub use crate::application_states::*;
+
+// Shown over the first page when we reopen a deck we've been in before.
+impl ApplicationScreenState for ResumePromptState {
+    fn handle_event(&self,
+                    app: &mut ApplicationState,
+                    graphics_context: &mut SDL2GraphicsContext,
+                    event_pump: &mut sdl2::EventPump,
+                    _delta_time: f32) {
+        for event in event_pump.poll_iter() {
+            match event {
+                SDLEvent::DropFile { filename, .. } => {
+                    app.open_slideshow(graphics_context, &filename);
+                },
+                SDLEvent::Quit {..} => {
+                    app.state = ApplicationScreen::Quit(QuitState);
+                },
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } => {
+                    match app.keymap.action(keycode) {
+                        Some(InputAction::Accept) | Some(InputAction::NextPage) => {
+                            if let Some(slideshow) = &mut app.slideshow {
+                                slideshow.set_current_page(self.page);
+                            }
+                            app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                        },
+                        Some(InputAction::Cancel) | Some(InputAction::Back) | Some(InputAction::FirstPage) => {
+                            app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                        },
+                        Some(InputAction::ToggleFullscreen) => {
+                            app.toggle_fullscreen(graphics_context);
+                        },
+                        Some(InputAction::Quit) => {
+                            app.state = ApplicationScreen::Quit(QuitState);
+                        },
+                        _ => {}
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&self,
+            app: &ApplicationState,
+            graphics_context: &mut SDL2GraphicsContext) {
+        let default_font = graphics_context.add_font(&app.settings.default_font);
+        graphics_context.clear_color(Color::new(0, 0, 0, 255));
+
+        if let Some(slideshow) = &app.slideshow {
+            graphics_context.camera = Camera::default();
+            slideshow.try_to_draw_page(graphics_context, default_font, slideshow.current_page() as usize);
+        }
+
+        graphics_context.use_viewport_default();
+        graphics_context.logical_resolution = VirtualResolution::Display;
+
+        let (width, height) = (graphics_context.logical_width() as f32, graphics_context.logical_height() as f32);
+        graphics_context.render_filled_rectangle(0.0, 0.0, width, height, Color::new(10, 10, 16, 200));
+
+        let font_size = graphics_context.font_size_percent(0.06);
+        graphics_context.render_text_justified(default_font,
+                                               TextBounds::ScreenLine(0.0, height / 2.0 - font_size as f32 * 1.5),
+                                               TextJustification::center(),
+                                               &format!("Resume at page {}?", self.page + 1),
+                                               font_size,
+                                               COLOR_RIPE_LEMON,
+                                               sdl2::ttf::FontStyle::NORMAL);
+        graphics_context.render_text_justified(default_font,
+                                               TextBounds::ScreenLine(0.0, height / 2.0 + font_size as f32 * 0.5),
+                                               TextJustification::center(),
+                                               "Enter: resume, Escape: start from the beginning",
+                                               graphics_context.font_size_percent(0.035),
+                                               COLOR_WHITE,
+                                               sdl2::ttf::FontStyle::NORMAL);
+    }
+}
@@ -12,56 +12,55 @@ impl ApplicationScreenState for SelectSlideToLoadState {
         for event in event_pump.poll_iter() {
             match event {
                 SDLEvent::DropFile { filename, .. } => {
-                    graphics_context.clear_resources();
-
-                    let new_slide = Slide::new_from_file(&filename);
-                    app.slideshow = new_slide;
-
-                    app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                    app.open_slideshow(graphics_context, &filename);
                 },
                 SDLEvent::Quit {..} => {
                     app.state = ApplicationScreen::Quit(QuitState);
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Return), .. } |
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Right), .. }  =>  {
-                    let directory_listing = std::fs::read_dir(&app.current_working_directory).expect("Failed to get directory listing?");
-                    let selected_path = directory_listing.into_iter().nth(app.currently_selected_directory);
-
-                    if let Some(path) = selected_path {
-                        let path = path.expect("bad permission?");
-                        let file_type = path.file_type().unwrap();
-
-                        if file_type.is_dir() {
-                            app.current_working_directory = path.path();
-                            app.currently_selected_directory = 0;
-                        } else {
-                            graphics_context.clear_resources();
-
-                            let new_slide = Slide::new_from_file(path.path().to_str().expect("bad unicode"));
-                            app.slideshow = new_slide;
-
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } => {
+                    match app.keymap.action(keycode) {
+                        Some(InputAction::Accept) | Some(InputAction::NextPage) => {
+                            let directory_listing = std::fs::read_dir(&app.current_working_directory).expect("Failed to get directory listing?");
+                            let selected_path = directory_listing.into_iter().nth(app.currently_selected_directory);
+
+                            if let Some(path) = selected_path {
+                                let path = path.expect("bad permission?");
+                                let file_type = path.file_type().unwrap();
+
+                                if file_type.is_dir() {
+                                    app.current_working_directory = path.path();
+                                    app.currently_selected_directory = 0;
+                                    app.remember_current_working_directory();
+                                } else {
+                                    app.open_slideshow(graphics_context, path.path().to_str().expect("bad unicode"));
+                                }
+                            }
+                        },
+                        Some(InputAction::Back) | Some(InputAction::PreviousPage) => {
+                            app.current_working_directory.pop();
+                            app.remember_current_working_directory();
+                        },
+                        Some(InputAction::SelectionUp) if app.currently_selected_directory > 0 => {
+                            app.currently_selected_directory -= 1;
+                        },
+                        Some(InputAction::SelectionDown) => {
+                            app.currently_selected_directory += 1;
+                        },
+                        Some(InputAction::ToggleFullscreen) => {
+                            app.toggle_fullscreen(graphics_context);
+                        },
+                        Some(InputAction::Options) => {
+                            app.state = ApplicationScreen::Options(OptionsState);
+                        },
+                        Some(InputAction::Cancel) | Some(InputAction::OpenBrowser) => {
                             app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
-                        }
+                        },
+                        Some(InputAction::Quit) => {
+                            app.state = ApplicationScreen::Quit(QuitState);
+                        },
+                        _ => {}
                     }
                 },
-
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Left), .. } => {
-                    app.current_working_directory.pop();
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Up), .. } => {
-                    if app.currently_selected_directory > 0 {
-                        app.currently_selected_directory -= 1;
-                    }
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Down), .. } => {
-                    app.currently_selected_directory += 1;
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::O), .. } => {
-                    app.state = ApplicationScreen::Options(OptionsState);
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::L), .. } => {
-                    app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
-                },
                 _ => {
                     #[cfg(debug_assertions)]
                     {
@@ -75,7 +74,7 @@ impl ApplicationScreenState for SelectSlideToLoadState {
     fn draw(&self,
             app: &ApplicationState,
             graphics_context: &mut SDL2GraphicsContext) {
-        let default_font = graphics_context.add_font("data/fonts/libre-baskerville/LibreBaskerville-Regular.ttf");
+        let default_font = graphics_context.add_font(&app.settings.default_font);
 
         graphics_context.logical_resolution = VirtualResolution::Display;
         graphics_context.clear_color(Color::new(10, 10, 16, 255));
@@ -96,7 +95,7 @@ impl ApplicationScreenState for SelectSlideToLoadState {
                                      heading_height as f32,
                                      #[cfg(target_os = "windows")] // Weird thing that looks like a drive root? ?//?DRIVE_LETTER:/
                                      &format!("{}", &app.current_working_directory.to_str().unwrap())[4..],
-                                     #[cfg(target_os = "unix")]
+                                     #[cfg(not(target_os = "windows"))]
                                      &format!("{}", &app.current_working_directory.to_str().unwrap()),
                                      heading_font_size,
                                      Color::new(128, 128, 128, 255),
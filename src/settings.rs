@@ -0,0 +1,187 @@
+/*
+    User settings, kept in <config directory>/slideshow/settings.conf
+
+    Same format as the keymap, "key = value" with # comments. Keys this version doesn't
+    know about are kept around and written back out, so an older build won't eat the
+    settings of a newer one.
+*/
+use std::path::PathBuf;
+
+pub const SETTINGS_FILE_NAME : &str = "settings.conf";
+pub const SETTINGS_SCHEMA_VERSION : u32 = 1;
+
+pub const DEFAULT_FONT : &str = "data/fonts/libre-baskerville/LibreBaskerville-Regular.ttf";
+pub const DEFAULT_HOT_RELOAD_INTERVAL : f32 = 0.20;
+// megabytes of text textures kept around by render_static_text.
+pub const DEFAULT_TEXT_CACHE_BUDGET : u32 = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub window_resolution: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    pub last_directory: Option<PathBuf>,
+    pub default_font: String,
+    pub hot_reload_interval: f32,
+    pub text_cache_budget: u32,
+
+    unknown_entries: Vec<(String, String)>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            window_resolution: None,
+            fullscreen: false,
+            last_directory: None,
+            default_font: DEFAULT_FONT.to_owned(),
+            hot_reload_interval: DEFAULT_HOT_RELOAD_INTERVAL,
+            text_cache_budget: DEFAULT_TEXT_CACHE_BUDGET,
+            unknown_entries: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn new_from_source(source: &str) -> Settings {
+        let mut settings = Settings::default();
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = match parts.next() {
+                Some(value) => value.trim(),
+                None => {
+                    println!("warning: {}:{}: expected key = value", SETTINGS_FILE_NAME, line_number+1);
+                    continue;
+                }
+            };
+
+            match key {
+                "version" => {
+                    match value.parse::<u32>() {
+                        Ok(version) if version > SETTINGS_SCHEMA_VERSION => {
+                            println!("warning: {} is from a newer version ({}), reading what I can.", SETTINGS_FILE_NAME, version);
+                        },
+                        Ok(_) => {},
+                        Err(_) => {
+                            println!("warning: {}:{}: bad version \"{}\"", SETTINGS_FILE_NAME, line_number+1, value);
+                        },
+                    }
+                },
+                "window_resolution" => {
+                    let mut dimensions = value.splitn(2, 'x').map(|dimension| dimension.trim().parse::<u32>());
+                    settings.window_resolution =
+                        match (dimensions.next(), dimensions.next()) {
+                            (Some(Ok(width)), Some(Ok(height))) => Some((width, height)),
+                            _ => None,
+                        };
+                },
+                "fullscreen" => {
+                    settings.fullscreen = value == "true";
+                },
+                "last_directory" => {
+                    if !value.is_empty() {
+                        settings.last_directory = Some(PathBuf::from(value));
+                    }
+                },
+                "default_font" => {
+                    if !value.is_empty() {
+                        settings.default_font = value.to_owned();
+                    }
+                },
+                "hot_reload_interval" => {
+                    settings.hot_reload_interval = value.parse::<f32>()
+                        .ok()
+                        .filter(|interval| *interval > 0.0)
+                        .unwrap_or(DEFAULT_HOT_RELOAD_INTERVAL);
+                },
+                "text_cache_budget" => {
+                    settings.text_cache_budget = value.parse::<u32>()
+                        .ok()
+                        .filter(|budget| *budget > 0)
+                        .unwrap_or(DEFAULT_TEXT_CACHE_BUDGET);
+                },
+                _ => {
+                    settings.unknown_entries.push((key.to_owned(), value.to_owned()));
+                }
+            }
+        }
+
+        settings
+    }
+
+    pub fn to_source(&self) -> String {
+        let mut source = String::from("# slideshow settings, this file is rewritten when options change.\n");
+        source.push_str(&format!("version = {}\n", SETTINGS_SCHEMA_VERSION));
+        if let Some((width, height)) = self.window_resolution {
+            source.push_str(&format!("window_resolution = {}x{}\n", width, height));
+        }
+        source.push_str(&format!("fullscreen = {}\n", self.fullscreen));
+        if let Some(directory) = self.last_directory.as_ref().and_then(|directory| directory.to_str()) {
+            source.push_str(&format!("last_directory = {}\n", directory));
+        }
+        source.push_str(&format!("default_font = {}\n", self.default_font));
+        source.push_str(&format!("hot_reload_interval = {}\n", self.hot_reload_interval));
+        source.push_str(&format!("text_cache_budget = {}\n", self.text_cache_budget));
+        for (key, value) in &self.unknown_entries {
+            source.push_str(&format!("{} = {}\n", key, value));
+        }
+        source
+    }
+
+    fn file_path() -> Option<PathBuf> {
+        crate::utility::user_config_directory().map(|directory| directory.join(SETTINGS_FILE_NAME))
+    }
+
+    pub fn load() -> Settings {
+        use crate::utility::*;
+        match Settings::file_path().as_ref().and_then(|path| path.to_str()).map(load_file) {
+            Some(Ok(source)) => Settings::new_from_source(&source),
+            _ => Settings::default(),
+        }
+    }
+
+    // Not being able to save settings is not worth crashing over.
+    pub fn save(&self) {
+        if let Some(path) = Settings::file_path() {
+            if let Some(directory) = path.parent() {
+                if let Err(error) = std::fs::create_dir_all(directory) {
+                    println!("warning: could not create {}: {}", directory.display(), error);
+                    return;
+                }
+            }
+
+            if let Err(error) = std::fs::write(&path, self.to_source()) {
+                println!("warning: could not save {}: {}", path.display(), error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn settings_round_trip() {
+    let settings = Settings {
+        window_resolution: Some((1920, 1080)),
+        fullscreen: true,
+        last_directory: Some(PathBuf::from("/home/someone/talks")),
+        default_font: String::from("data/fonts/Inconsolata/Inconsolata-Regular.ttf"),
+        hot_reload_interval: 0.5,
+        text_cache_budget: 16,
+        unknown_entries: vec![(String::from("from_the_future"), String::from("yes"))],
+    };
+    assert_eq!(settings, Settings::new_from_source(&settings.to_source()));
+}
+#[test]
+fn settings_bad_values_use_defaults() {
+    let settings = Settings::new_from_source("version = 1\nwindow_resolution = big\nhot_reload_interval = -3\ntext_cache_budget = 0\ngarbage\n");
+    assert_eq!(None, settings.window_resolution);
+    assert_eq!(DEFAULT_HOT_RELOAD_INTERVAL, settings.hot_reload_interval);
+    assert_eq!(DEFAULT_TEXT_CACHE_BUDGET, settings.text_cache_budget);
+    assert_eq!(DEFAULT_FONT, settings.default_font);
+}
@@ -0,0 +1,307 @@
+/*
+    Rectangles, circles, lines and arrows for $rect, $circle, $line and $arrow.
+
+    SDL only draws hard edged rectangles and one pixel lines, so shapes are turned
+    into outlines here and filled with the same coverage rasterizer the glyphs go
+    through, which gives them antialiased edges. The pixels end up in a texture,
+    drawn like an image until the shape is needed at a different size.
+
+    Outlines go one way and holes the other, so a stroke is the outline pushed out
+    by half the stroke width with the outline pulled in by half cut out of it.
+*/
+use ab_glyph_rasterizer::{point, Point, Rasterizer};
+
+use crate::color::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeStyle {
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    pub stroke_width: f32,
+}
+
+// In slide coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Rect { x: f32, y: f32, w: f32, h: f32, corner_radius: f32 },
+    Circle { x: f32, y: f32, radius: f32 },
+    // the arrowhead goes on x2, y2.
+    Line { x1: f32, y1: f32, x2: f32, y2: f32, arrow: bool },
+}
+
+// White on nothing is what's left over, width * height RGBA pixels that go at
+// left, top in slide coordinates.
+pub struct RasterizedShape {
+    pub left: f32,
+    pub top: f32,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+type Outline = Vec<Point>;
+
+fn arrowhead_length(stroke_width: f32) -> f32 {
+    (stroke_width * 4.0).max(12.0)
+}
+
+// Enough points that a curve still looks like one at this many pixels.
+fn arc_steps(radius: f32, scale: f32) -> usize {
+    ((radius * scale).sqrt() * 2.0).ceil().clamp(2.0, 64.0) as usize
+}
+
+fn circle_outline(x: f32, y: f32, radius: f32, scale: f32) -> Outline {
+    let steps = arc_steps(radius, scale) * 4;
+    (0..steps)
+        .map(|step| {
+            let angle = step as f32 / steps as f32 * std::f32::consts::TAU;
+            point(x + radius * angle.cos(), y + radius * angle.sin())
+        })
+        .collect()
+}
+
+fn rounded_rect_outline(x: f32, y: f32, w: f32, h: f32, corner_radius: f32, scale: f32) -> Outline {
+    let radius = corner_radius.min(w / 2.0).min(h / 2.0).max(0.0);
+    if radius == 0.0 {
+        return vec![point(x, y), point(x + w, y), point(x + w, y + h), point(x, y + h)];
+    }
+
+    let steps = arc_steps(radius, scale);
+    // top left, top right, bottom right, bottom left, each a quarter turn on from the last.
+    let corners = [(x + radius, y + radius), (x + w - radius, y + radius), (x + w - radius, y + h - radius), (x + radius, y + h - radius)];
+    let mut outline = Vec::with_capacity((steps + 1) * 4);
+    for (corner, (center_x, center_y)) in corners.iter().enumerate() {
+        for step in 0..=steps {
+            let angle = (corner as f32 + 2.0 + step as f32 / steps as f32) * std::f32::consts::FRAC_PI_2;
+            outline.push(point(center_x + radius * angle.cos(), center_y + radius * angle.sin()));
+        }
+    }
+    outline
+}
+
+fn signed_area(outline: &[Point]) -> f32 {
+    outline.iter().zip(outline.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum::<f32>() / 2.0
+}
+
+// The rasterizer only cuts lines off at the top and bottom, past the sides they'd
+// land on the row above or below. Whatever is left of the edge still covers the
+// pixels to its right, so it's squashed onto the edge. Past the right it's squashed
+// onto the two spare columns the rasterizer has, which are never drawn.
+fn draw_clipped_line(rasterizer: &mut Rasterizer, from: Point, to: Point, right: f32) {
+    let mut cuts = vec![0.0, 1.0];
+    for edge in [0.0, right] {
+        let cut = (edge - from.x) / (to.x - from.x);
+        if cut > 0.0 && cut < 1.0 {
+            cuts.push(cut);
+        }
+    }
+    cuts.sort_by(f32::total_cmp);
+
+    let along = |cut: f32| point((from.x + (to.x - from.x) * cut).clamp(0.0, right),
+                                 from.y + (to.y - from.y) * cut);
+    for cut in cuts.windows(2) {
+        rasterizer.draw_line(along(cut[0]), along(cut[1]));
+    }
+}
+
+// Holes go the other way round, so they cancel out whatever they're inside of.
+fn draw_outline(rasterizer: &mut Rasterizer, outline: &[Point], hole: bool, right: f32, to_pixels: impl Fn(Point) -> Point) {
+    let mut outline : Vec<Point> = outline.iter().map(|point| to_pixels(*point)).collect();
+    if (signed_area(&outline) < 0.0) != hole {
+        outline.reverse();
+    }
+    for (from, to) in outline.iter().zip(outline.iter().cycle().skip(1)) {
+        draw_clipped_line(rasterizer, *from, *to, right);
+    }
+}
+
+impl Shape {
+    // The smallest box around it, strokes and arrowheads included, as left, top, right, bottom.
+    pub fn bounds(&self, style: &ShapeStyle) -> (f32, f32, f32, f32) {
+        let half_stroke = if style.stroke.is_some() { style.stroke_width / 2.0 } else { 0.0 };
+        match *self {
+            Shape::Rect { x, y, w, h, .. } => (x - half_stroke, y - half_stroke, x + w + half_stroke, y + h + half_stroke),
+            Shape::Circle { x, y, radius } => (x - radius - half_stroke, y - radius - half_stroke, x + radius + half_stroke, y + radius + half_stroke),
+            Shape::Line { x1, y1, x2, y2, arrow } => {
+                let padding = if arrow { arrowhead_length(style.stroke_width) } else { half_stroke };
+                (x1.min(x2) - padding, y1.min(y2) - padding, x1.max(x2) + padding, y1.max(y2) + padding)
+            },
+        }
+    }
+
+    // (outline, is a hole) for the inside and for the stroke.
+    fn outlines(&self, style: &ShapeStyle, scale: f32) -> (Vec<Outline>, Vec<(Outline, bool)>) {
+        let half_stroke = style.stroke_width / 2.0;
+        match *self {
+            Shape::Rect { x, y, w, h, corner_radius } => {
+                let mut stroke = vec![(rounded_rect_outline(x - half_stroke, y - half_stroke,
+                                                            w + style.stroke_width, h + style.stroke_width,
+                                                            corner_radius + half_stroke, scale), false)];
+                if w > style.stroke_width && h > style.stroke_width {
+                    stroke.push((rounded_rect_outline(x + half_stroke, y + half_stroke,
+                                                      w - style.stroke_width, h - style.stroke_width,
+                                                      corner_radius - half_stroke, scale), true));
+                }
+                (vec![rounded_rect_outline(x, y, w, h, corner_radius, scale)], stroke)
+            },
+            Shape::Circle { x, y, radius } => {
+                let mut stroke = vec![(circle_outline(x, y, radius + half_stroke, scale), false)];
+                if radius > half_stroke {
+                    stroke.push((circle_outline(x, y, radius - half_stroke, scale), true));
+                }
+                (vec![circle_outline(x, y, radius, scale)], stroke)
+            },
+            Shape::Line { x1, y1, x2, y2, arrow } => {
+                let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+                if length == 0.0 {
+                    return (Vec::new(), Vec::new());
+                }
+                let (along_x, along_y) = ((x2 - x1) / length, (y2 - y1) / length);
+                let (across_x, across_y) = (-along_y * half_stroke, along_x * half_stroke);
+
+                let mut stroke = Vec::new();
+                // the line stops a little inside the arrowhead, so there's no seam.
+                let head_length = if arrow { arrowhead_length(style.stroke_width).min(length) } else { 0.0 };
+                let end_x = x2 - along_x * head_length * 0.9;
+                let end_y = y2 - along_y * head_length * 0.9;
+                stroke.push((vec![point(x1 + across_x, y1 + across_y), point(end_x + across_x, end_y + across_y),
+                                  point(end_x - across_x, end_y - across_y), point(x1 - across_x, y1 - across_y)], false));
+                if arrow {
+                    let base_x = x2 - along_x * head_length;
+                    let base_y = y2 - along_y * head_length;
+                    let (wing_x, wing_y) = (-along_y * head_length / 2.0, along_x * head_length / 2.0);
+                    stroke.push((vec![point(x2, y2), point(base_x + wing_x, base_y + wing_y), point(base_x - wing_x, base_y - wing_y)], false));
+                }
+                (Vec::new(), stroke)
+            },
+        }
+    }
+}
+
+// scale is how many pixels one unit of slide is. Only what's inside visible (left,
+// top, right, bottom in slide coordinates) is rasterized, so a shape way bigger than
+// the slide doesn't need any more pixels than one that fills it.
+pub fn rasterize_shape(shape: &Shape, style: &ShapeStyle, scale: f32, visible: (f32, f32, f32, f32)) -> RasterizedShape {
+    let (visible_left, visible_top, visible_right, visible_bottom) = visible;
+    // a pixel either side for the antialiasing.
+    let (left, top, right, bottom) = shape.bounds(style);
+    let (left, top) = ((left - 1.0 / scale).max(visible_left), (top - 1.0 / scale).max(visible_top));
+    let (right, bottom) = (right.min(visible_right), bottom.min(visible_bottom));
+    // nothing at all when it's entirely off the slide (or NaN).
+    let pixels_across = |from: f32, to: f32| if to > from { ((to - from) * scale).ceil() as usize + 1 } else { 0 };
+    let (width, height) = (pixels_across(left, right), pixels_across(top, bottom));
+    if width == 0 || height == 0 {
+        return RasterizedShape { left, top, width: 0, height: 0, pixels: Vec::new() };
+    }
+    let to_pixels = |slide_point: Point| point((slide_point.x - left) * scale, (slide_point.y - top) * scale);
+
+    let (fill_outlines, stroke_outlines) = shape.outlines(style, scale);
+    let coverage = |outlines: &[(Outline, bool)]| {
+        let rasterizer_width = width + 2;
+        let mut rasterizer = Rasterizer::new(rasterizer_width, height);
+        for (outline, hole) in outlines {
+            draw_outline(&mut rasterizer, outline, *hole, width as f32, to_pixels);
+        }
+        let mut coverage = vec![0.0; width * height];
+        rasterizer.for_each_pixel(|index, alpha| {
+            let (x, y) = (index % rasterizer_width, index / rasterizer_width);
+            if x < width {
+                coverage[y * width + x] = alpha.min(1.0);
+            }
+        });
+        coverage
+    };
+
+    let no_coverage = vec![0.0; width * height];
+    let fill_coverage = match style.fill {
+        Some(_) => coverage(&fill_outlines.into_iter().map(|outline| (outline, false)).collect::<Vec<_>>()),
+        None => no_coverage.clone(),
+    };
+    let stroke_coverage = match style.stroke {
+        Some(_) if style.stroke_width > 0.0 => coverage(&stroke_outlines),
+        _ => no_coverage,
+    };
+
+    // the stroke goes over the fill.
+    let fill = style.fill.unwrap_or(Color::new(0, 0, 0, 0));
+    let stroke = style.stroke.unwrap_or(Color::new(0, 0, 0, 0));
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for (fill_coverage, stroke_coverage) in fill_coverage.iter().zip(&stroke_coverage) {
+        let fill_alpha = fill.a as f32 / 255.0 * fill_coverage;
+        let stroke_alpha = stroke.a as f32 / 255.0 * stroke_coverage;
+        let alpha = stroke_alpha + fill_alpha * (1.0 - stroke_alpha);
+        let channel = |fill_channel: u8, stroke_channel: u8| {
+            if alpha == 0.0 {
+                0
+            } else {
+                ((stroke_channel as f32 * stroke_alpha + fill_channel as f32 * fill_alpha * (1.0 - stroke_alpha)) / alpha).round() as u8
+            }
+        };
+        pixels.push(channel(fill.r, stroke.r));
+        pixels.push(channel(fill.g, stroke.g));
+        pixels.push(channel(fill.b, stroke.b));
+        pixels.push((alpha * 255.0).round() as u8);
+    }
+
+    RasterizedShape { left, top, width: width as u32, height: height as u32, pixels }
+}
+
+#[cfg(test)]
+const SLIDE : (f32, f32, f32, f32) = (0.0, 0.0, 1280.0, 720.0);
+
+#[cfg(test)]
+fn alpha_at(shape: &RasterizedShape, x: f32, y: f32) -> u8 {
+    let x = (x - shape.left) as u32;
+    let y = (y - shape.top) as u32;
+    shape.pixels[((y * shape.width + x) * 4 + 3) as usize]
+}
+
+#[cfg(test)]
+#[test]
+fn strokes_leave_the_middle_empty() {
+    let red = Some(Color::new(255, 0, 0, 255));
+    let outlined = ShapeStyle { fill: None, stroke: red, stroke_width: 4.0 };
+    let circle = rasterize_shape(&Shape::Circle { x: 50.0, y: 50.0, radius: 20.0 }, &outlined, 1.0, SLIDE);
+    assert_eq!(0, alpha_at(&circle, 50.0, 50.0));
+    assert_eq!(255, alpha_at(&circle, 50.0, 30.0));
+    assert_eq!(0, alpha_at(&circle, 50.0, 25.0));
+
+    let filled = ShapeStyle { fill: red, stroke: None, stroke_width: 0.0 };
+    let rect = rasterize_shape(&Shape::Rect { x: 10.0, y: 10.0, w: 30.0, h: 20.0, corner_radius: 8.0 }, &filled, 1.0, SLIDE);
+    assert_eq!(255, alpha_at(&rect, 25.0, 20.0));
+    // the corner is rounded off.
+    assert_eq!(0, alpha_at(&rect, 10.0, 10.0));
+    // the edges are antialiased rather than all or nothing.
+    assert!(rect.pixels.chunks(4).any(|pixel| pixel[3] > 0 && pixel[3] < 255));
+}
+#[test]
+fn arrowheads_add_to_the_line() {
+    let style = ShapeStyle { fill: None, stroke: Some(Color::new(0, 0, 0, 255)), stroke_width: 4.0 };
+    let line = rasterize_shape(&Shape::Line { x1: 10.0, y1: 50.0, x2: 90.0, y2: 50.0, arrow: false }, &style, 1.0, SLIDE);
+    let arrow = rasterize_shape(&Shape::Line { x1: 10.0, y1: 50.0, x2: 90.0, y2: 50.0, arrow: true }, &style, 1.0, SLIDE);
+    assert_eq!(255, alpha_at(&line, 50.0, 50.0));
+    assert_eq!(0, alpha_at(&line, 50.0, 52.0));
+    // where the line and the arrowhead overlap stays solid.
+    assert_eq!(255, alpha_at(&arrow, 74.0, 50.0));
+    assert_eq!(255, alpha_at(&arrow, 85.0, 51.0));
+    assert_eq!(0, alpha_at(&arrow, 85.0, 55.0));
+}
+#[test]
+fn shapes_are_cut_off_at_the_slide() {
+    let filled = ShapeStyle { fill: Some(Color::new(0, 0, 255, 255)), stroke: None, stroke_width: 0.0 };
+    let huge = rasterize_shape(&Shape::Rect { x: -1.0e9, y: -1.0e9, w: 2.0e9, h: 2.0e9, corner_radius: 0.0 }, &filled, 2.0, SLIDE);
+    assert!(huge.width <= 1280 * 2 + 1 && huge.height <= 720 * 2 + 1);
+    assert!(huge.pixels.chunks(4).all(|pixel| pixel[3] == 255));
+
+    // hanging off the left and right, what's left is still filled in properly.
+    let circle = rasterize_shape(&Shape::Circle { x: 10.0, y: 50.0, radius: 30.0 }, &filled, 1.0, (0.0, 0.0, 30.0, 100.0));
+    assert_eq!(0.0, circle.left);
+    assert_eq!(255, alpha_at(&circle, 0.0, 50.0));
+    assert_eq!(255, alpha_at(&circle, 29.0, 50.0));
+    assert_eq!(0, alpha_at(&circle, 0.0, 18.0));
+
+    let gone = rasterize_shape(&Shape::Circle { x: -100.0, y: 50.0, radius: 30.0 }, &filled, 1.0, SLIDE);
+    assert!(gone.pixels.is_empty());
+}
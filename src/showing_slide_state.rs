@@ -1,5 +1,59 @@
 pub use crate::application_states::*;
 
+// Moves with one of Slide's page methods. Jumps play the transition of the page
+// being jumped to, if it has one.
+fn change_page(app: &mut ApplicationState, move_page: impl FnOnce(&mut Slide) -> isize) {
+    if let Some(slideshow) = &mut app.slideshow {
+        let from = slideshow.current_page();
+        let to = move_page(slideshow);
+
+        if from != to {
+            app.state = ApplicationScreen::ChangePage(
+                ChangePageState{
+                    from,
+                    to,
+                    transition_page: to,
+                });
+        }
+    }
+}
+
+fn jump_to_page(app: &mut ApplicationState, to: isize) {
+    change_page(app, |slideshow| slideshow.set_current_page(to));
+}
+
+// Code highlights on the page are stepped through before it's left.
+fn go_to_next_page(app: &mut ApplicationState) {
+    app.start_presentation_timer();
+    if let Some(slideshow) = &mut app.slideshow {
+        if slideshow.next_step() {
+            return;
+        }
+        let from = slideshow.current_page();
+        app.state = ApplicationScreen::ChangePage(
+            ChangePageState{
+                from,
+                to: slideshow.next_page(),
+                transition_page: from,
+            });
+    }
+}
+
+fn go_to_previous_page(app: &mut ApplicationState) {
+    if let Some(slideshow) = &mut app.slideshow {
+        if slideshow.previous_step() {
+            return;
+        }
+        let from = slideshow.current_page();
+        app.state = ApplicationScreen::ChangePage(
+            ChangePageState{
+                from,
+                to: slideshow.previous_page(),
+                transition_page: from,
+            });
+    }
+}
+
 impl ApplicationScreenState for ShowingSlideState {
     fn handle_event(&self,
                     app: &mut ApplicationState,
@@ -9,53 +63,125 @@ impl ApplicationScreenState for ShowingSlideState {
         for event in event_pump.poll_iter() {
             match event {
                 SDLEvent::DropFile { filename, .. } => {
-                    graphics_context.clear_resources();
-
-                    let new_slide = Slide::new_from_file(&filename);
-                    app.slideshow = new_slide;
-
-                    app.state = ApplicationScreen::ShowingSlide(ShowingSlideState);
+                    app.open_slideshow(graphics_context, &filename);
                 },
                 SDLEvent::Quit {..} => {
                     app.state = ApplicationScreen::Quit(QuitState);
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Escape), .. } =>  {
-                    graphics_context.clear_resources();
-                    app.slideshow = None;
-                    app.state = ApplicationScreen::InvalidOrNoSlide(InvalidOrNoSlideState);
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::F), ..} => {
-                    graphics_context.toggle_fullscreen();
-                },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::R), .. } => {
-                    if let Some(slideshow) = &mut app.slideshow {
-                        graphics_context.clear_resources();
-                        slideshow.reload().expect("should be successful");
+                // typing a page number isn't something you'd want to rebind. Six digits
+                // is more pages than anybody has, so any more are just ignored.
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } if keycode_to_digit(keycode).is_some() => {
+                    if app.typed_page_number.len() < 6 {
+                        app.typed_page_number.push(keycode_to_digit(keycode).unwrap());
                     }
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Right), .. } => {
-                    if let Some(slideshow) = &mut app.slideshow {
-                        app.state = ApplicationScreen::ChangePage(
-                            ChangePageState{
-                                from: slideshow.current_page(),
-                                to: slideshow.next_page()
-                            });
+                SDLEvent::KeyDown { keycode: Some(keycode), .. } => {
+                    match app.keymap.action(keycode) {
+                        Some(InputAction::Cancel) => {
+                            app.typed_page_number.clear();
+                        },
+                        Some(InputAction::Back) => {
+                            if !app.typed_page_number.is_empty() {
+                                app.typed_page_number.pop();
+                            } else if let Some(page) = app.page_history.pop() {
+                                // "back" means the last link if we followed one.
+                                jump_to_page(app, page);
+                            } else {
+                                go_to_previous_page(app);
+                            }
+                        },
+                        Some(InputAction::Accept) => {
+                            // pages are typed the way they're shown, starting from 1.
+                            if let Ok(page_number) = app.typed_page_number.parse::<isize>() {
+                                jump_to_page(app, page_number - 1);
+                            }
+                            app.typed_page_number.clear();
+                        },
+                        Some(InputAction::FirstPage) => {
+                            change_page(app, Slide::first_page);
+                        },
+                        Some(InputAction::LastPage) => {
+                            change_page(app, Slide::last_page);
+                        },
+                        Some(InputAction::NextPage) => {
+                            go_to_next_page(app);
+                        },
+                        Some(InputAction::PreviousPage) => {
+                            go_to_previous_page(app);
+                        },
+                        Some(InputAction::BlackScreen) => {
+                            app.state = ApplicationScreen::BlankScreen(BlankScreenState { color: COLOR_BLACK });
+                        },
+                        Some(InputAction::WhiteScreen) => {
+                            app.state = ApplicationScreen::BlankScreen(BlankScreenState { color: COLOR_WHITE });
+                        },
+                        Some(InputAction::ToggleFullscreen) => {
+                            app.toggle_fullscreen(graphics_context);
+                        },
+                        Some(InputAction::ToggleDebugOverlay) => {
+                            app.show_debug_overlay = !app.show_debug_overlay;
+                        },
+                        Some(InputAction::Reload) => {
+                            graphics_context.clear_resources();
+                            app.reload_slideshow();
+                        },
+                        Some(InputAction::CloseSlideshow) => {
+                            graphics_context.clear_resources();
+                            app.close_slideshow();
+                            app.state = ApplicationScreen::InvalidOrNoSlide(InvalidOrNoSlideState);
+                        },
+                        Some(InputAction::OpenBrowser) => {
+                            app.state = ApplicationScreen::SelectSlideToLoad(SelectSlideToLoadState);
+                        },
+                        Some(InputAction::Options) => {
+                            app.state = ApplicationScreen::Options(OptionsState);
+                        },
+                        Some(InputAction::Presenter) => {
+                            app.state = ApplicationScreen::Presenter(PresenterState);
+                        },
+                        Some(InputAction::Overview) => {
+                            if let Some(slideshow) = &app.slideshow {
+                                app.currently_selected_overview_page = slideshow.current_page() as usize;
+                                app.state = ApplicationScreen::Overview(OverviewState);
+                            }
+                        },
+                        Some(InputAction::Quit) => {
+                            app.state = ApplicationScreen::Quit(QuitState);
+                        },
+                        _ => {}
                     }
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::Left), .. } => {
-                    if let Some(slideshow) = &mut app.slideshow {
-                        app.state = ApplicationScreen::ChangePage(
-                            ChangePageState{
-                                from: slideshow.current_page(),
-                                to: slideshow.previous_page()
-                            });
+                SDLEvent::MouseButtonDown { mouse_btn: sdl2::mouse::MouseButton::Left, x, y, .. } => {
+                    let link_target = graphics_context.clickable_region_at(x, y).map(|region| region.target.clone());
+                    match (&app.slideshow, link_target) {
+                        (Some(slideshow), Some(target)) => {
+                            match slideshow.find_page_by_link(&target) {
+                                // a link to the page we're on goes nowhere, so back shouldn't stop there.
+                                Some(page) if page as isize == slideshow.current_page() => {},
+                                Some(page) => {
+                                    let current_page = slideshow.current_page();
+                                    app.push_page_history(current_page);
+                                    jump_to_page(app, page as isize);
+                                },
+                                None => {
+                                    println!("warning: link to \"{}\" does not match any page", target);
+                                },
+                            }
+                        },
+                        _ => {
+                            go_to_next_page(app);
+                        }
                     }
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::L), .. } => {
-                    app.state = ApplicationScreen::SelectSlideToLoad(SelectSlideToLoadState);
+                SDLEvent::MouseButtonDown { mouse_btn: sdl2::mouse::MouseButton::Right, .. } => {
+                    go_to_previous_page(app);
                 },
-                SDLEvent::KeyDown { keycode: Some(SDLKeycode::O), .. } => {
-                    app.state = ApplicationScreen::Options(OptionsState);
+                SDLEvent::MouseWheel { y, .. } => {
+                    if y < 0 {
+                        go_to_next_page(app);
+                    } else if y > 0 {
+                        go_to_previous_page(app);
+                    }
                 },
                 _ => {}
             }
@@ -65,13 +191,33 @@ impl ApplicationScreenState for ShowingSlideState {
     fn draw(&self,
             app: &ApplicationState,
             graphics_context: &mut SDL2GraphicsContext) {
-        let default_font = graphics_context.add_font("data/fonts/libre-baskerville/LibreBaskerville-Regular.ttf");
+        let default_font = graphics_context.add_font(&app.settings.default_font);
         if let Some(slideshow) = &app.slideshow {
             // graphics_context.camera.set_position(0.0, 0.0);
             graphics_context.camera.x = 0.0;
             graphics_context.camera.y = 0.0;
             graphics_context.clear_color(Color::new(0, 0, 0, 255));
             slideshow.try_to_draw_page(graphics_context, default_font, slideshow.current_page() as usize);
+
+            if !app.typed_page_number.is_empty() {
+                graphics_context.use_viewport_default();
+                graphics_context.logical_resolution = VirtualResolution::Display;
+
+                let font_size = graphics_context.font_size_percent(0.05);
+                let indicator = format!("Go to page: {}_", app.typed_page_number);
+                let (width, height) = graphics_context.text_dimensions(default_font, &indicator, font_size);
+                let (width, height) = (width as f32 + font_size as f32, height as f32);
+                let x = graphics_context.logical_width() as f32 - width - font_size as f32;
+                let y = graphics_context.logical_height() as f32 - height - font_size as f32;
+
+                graphics_context.render_filled_rectangle(x, y, width, height, Color::new(10, 10, 16, 200));
+                graphics_context.render_text(default_font,
+                                             x + font_size as f32 / 2.0, y,
+                                             &indicator,
+                                             font_size,
+                                             COLOR_RIPE_LEMON,
+                                             sdl2::ttf::FontStyle::NORMAL);
+            }
         }
     }
 
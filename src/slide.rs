@@ -1,6 +1,11 @@
 // TODO
 use crate::utility::*;
 use crate::color::*;
+use std::collections::HashMap;
+pub use crate::slide_parser::SlideCompileError;
+pub use crate::text_shaping::TextDirection;
+pub use crate::shapes::ShapeStyle;
+pub use crate::code_highlighting::{CodeSpan, CodeTheme};
 
 #[derive(Debug, Clone)]
 pub struct TextElement {
@@ -9,9 +14,12 @@ pub struct TextElement {
     pub y: Option<f32>,
     pub line_breaks: u32,
     pub text: String, // In the case I allow variables or something...
+    // text, already parsed.
+    pub markups: Vec<Markup>,
     pub color: Color,
     pub font_size: u16,
     pub font_name: Option<String>,
+    pub direction: TextDirection,
 }
 #[derive(Debug, Clone)]
 pub struct ImageElement {
@@ -24,10 +32,118 @@ pub struct ImageElement {
     pub color: Color,
 }
 
+// $code:language ... $end_code, already highlighted.
+#[derive(Debug, Clone)]
+pub struct CodeElement {
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub line_breaks: u32,
+    pub lines: Vec<Vec<CodeSpan>>,
+    pub font_size: u16,
+    pub line_numbers: bool,
+    pub theme: CodeTheme,
+    // highlight=3-5,8 is [[2, 3, 4], [7]], each step is one press of next page.
+    pub highlight_steps: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListItem {
+    pub level: usize,
+    // the bullet, or the number and its dot.
+    pub marker: String,
+    pub markups: Vec<Markup>,
+}
+
+// - item and 1. item lines, nested by how far in they're indented.
+#[derive(Debug, Clone)]
+pub struct ListElement {
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub line_breaks: u32,
+    // spaces in front of the outermost items, so a list stays where it was typed.
+    pub indent: usize,
+    pub items: Vec<ListItem>,
+    // by level.
+    pub font_sizes: Vec<u16>,
+    pub font_name: Option<String>,
+    pub color: Color,
+}
+
+impl ListElement {
+    pub fn font_size(&self, level: usize) -> u16 {
+        self.font_sizes.get(level).or(self.font_sizes.last()).copied().unwrap_or(48)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+// $table ... $end_table, rows of | separated cells like Markdown's.
+#[derive(Debug, Clone)]
+pub struct TableElement {
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub line_breaks: u32,
+    // the row above the |---| row, if there is one. Each cell is its markup.
+    pub header: Option<Vec<Vec<Markup>>>,
+    // every row has a cell for every column, even if it's empty.
+    pub rows: Vec<Vec<Vec<Markup>>>,
+    // by column.
+    pub alignments: Vec<CellAlignment>,
+    pub font_size: u16,
+    pub font_name: Option<String>,
+    pub color: Color,
+    // shades every other row.
+    pub zebra: bool,
+    // 0 is no borders.
+    pub border_width: f32,
+}
+
+// $rect:x:y:w:h
+#[derive(Debug, Clone)]
+pub struct RectElement {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    pub corner_radius: f32,
+    pub style: ShapeStyle,
+}
+
+// $circle:x:y:radius, x and y are the middle.
+#[derive(Debug, Clone)]
+pub struct CircleElement {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub style: ShapeStyle,
+}
+
+// $line:x1:y1:x2:y2, and $arrow, which points at x2, y2.
+#[derive(Debug, Clone)]
+pub struct LineElement {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+    pub style: ShapeStyle,
+}
+
 #[derive(Debug, Clone)]
 pub enum SlideElement {
     Text(TextElement),
     Image(ImageElement),
+    Code(CodeElement),
+    List(ListElement),
+    Table(TableElement),
+    Rect(RectElement),
+    Circle(CircleElement),
+    Line(LineElement),
+    Arrow(LineElement),
 }
 
 #[derive(Debug,Copy,Clone)]
@@ -58,19 +174,267 @@ impl SlideTransition {
 
 #[derive(Debug, Clone)]
 pub struct Page {
+    // $page:id=name, so links can find this page.
+    pub id: Option<String>,
     pub transition : Option<SlideTransition>,
 
     pub background_color: Color,
     pub elements: Vec<SlideElement>,
+
+    // speaker notes, only shown in the presenter view.
+    pub notes: Vec<String>,
+
+    // hash of the page's source, so a reload can tell which pages were edited.
+    pub source_hash: u64,
+}
+
+impl Page {
+    // How many times next page stays on this page to highlight code first.
+    pub fn steps(&self) -> usize {
+        self.elements.iter()
+            .map(|element| match element {
+                SlideElement::Code(code) => code.highlight_steps.len(),
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
 }
 
 use crate::graphics_context::*;
+use crate::code_highlighting::CODE_FONT;
+use crate::shapes::Shape;
+use crate::markup::{Markup, MarkupStyle};
+
+// The family a line is in (if it has one) and the font its plain text is drawn with.
+fn line_font<'a>(graphics_context: &mut SDL2GraphicsContext,
+                 font_name: Option<&'a String>,
+                 default_font: &'a str,
+                 fonts: &'a SlideFonts) -> (Option<&'a FontFamily>, &'a str) {
+    let font_family = fonts.family(font_name.map(|font| font.as_str()).unwrap_or(default_font));
+    let drawn_font =
+        if let Some(font_family) = font_family {
+            graphics_context.add_font(&font_family.regular)
+        } else if let Some(font) = font_name {
+            graphics_context.add_font(font)
+        } else {
+            default_font
+        };
+    (font_family, drawn_font)
+}
+
+// Some of a line of markup, with the font it's drawn in worked out.
+struct LinePiece<'a> {
+    markup: &'a Markup,
+    text: &'a str,
+    face: &'a str,
+    style: sdl2::ttf::FontStyle,
+    font_size: u16,
+    ascent: f32,
+    height: f32,
+}
+
+fn line_piece<'a>(graphics_context: &mut SDL2GraphicsContext,
+                  markup: &'a Markup,
+                  text: &'a str,
+                  (line_family, line_font): (Option<&'a FontFamily>, &'a str),
+                  font_size: u16,
+                  fonts: &'a SlideFonts) -> LinePiece<'a> {
+    let font_size = markup.size.unwrap_or(font_size);
+    let piece_family = match &markup.font {
+        Some(font) => fonts.family(font),
+        None => line_family,
+    };
+    let (face, style) =
+        if markup.style.contains(MarkupStyle::CODE) {
+            (CODE_FONT, markup.get_text_drawing_style())
+        } else {
+            match piece_family {
+                Some(font_family) => font_family.face_for_style(markup.get_text_drawing_style()),
+                None => (markup.font.as_deref().unwrap_or(line_font), markup.get_text_drawing_style()),
+            }
+        };
+    let face = graphics_context.add_font(face);
+    LinePiece {
+        markup,
+        text,
+        face,
+        style,
+        font_size,
+        ascent: graphics_context.font_ascent(face, font_size),
+        height: graphics_context.font_height(face, font_size) as f32,
+    }
+}
+
+fn line_piece_width(graphics_context: &mut SDL2GraphicsContext, piece: &LinePiece, text: &str, fallback_fonts: &[String]) -> f32 {
+    graphics_context.split_text_by_font_coverage(piece.face, fallback_fonts, text, piece.font_size)
+        .into_iter()
+        .map(|(run_font, run_text)| graphics_context.static_text_width(run_font, run_text, piece.font_size, piece.style) / graphics_context.camera.scale)
+        .sum()
+}
+
+// The line pieces are drawn on, its baseline is ascent below y.
+struct PieceLine<'a> {
+    y: f32,
+    ascent: f32,
+    // right to left pieces end at x rather than start there.
+    right_to_left: bool,
+    color: Color,
+    fallback_fonts: &'a [String],
+}
+
+// Draws a piece so it sits on the line's baseline. Returns how wide it was.
+fn render_line_piece(graphics_context: &mut SDL2GraphicsContext,
+                     piece: &LinePiece,
+                     x: f32,
+                     line: &PieceLine) -> f32 {
+    let LinePiece { markup, text, face, style, font_size, ascent, height } = *piece;
+    let PieceLine { right_to_left, fallback_fonts, .. } = *line;
+    let color = markup.color.unwrap_or(line.color);
+    let piece_y = line.y + line.ascent - ascent;
+
+    let runs = graphics_context.split_text_by_font_coverage(face, fallback_fonts, text, font_size);
+    if markup.style.contains(MarkupStyle::CODE) {
+        // the background goes down first, so it needs the width before anything is drawn.
+        let code_width = line_piece_width(graphics_context, piece, text, fallback_fonts);
+        let code_x = if right_to_left { x - code_width } else { x };
+        let padding = font_size as f32 / 6.0;
+        graphics_context.render_filled_rectangle(code_x - padding, piece_y,
+                                                 code_width + padding * 2.0, height,
+                                                 Color { a: 40, .. color });
+    }
+
+    let mut width = 0.0;
+    for (run_font, run_text) in runs {
+        if right_to_left {
+            // the cursor moves leftward, so each run goes to the left of the last.
+            width += graphics_context.static_text_width(run_font, run_text, font_size, style) / graphics_context.camera.scale;
+            graphics_context.render_static_text(run_font,
+                                                x - width, piece_y,
+                                                run_text,
+                                                font_size,
+                                                color,
+                                                style);
+        } else {
+            width += graphics_context.render_static_text(run_font,
+                                                         x + width, piece_y,
+                                                         run_text,
+                                                         font_size,
+                                                         color,
+                                                         style) / graphics_context.camera.scale;
+        }
+    }
+    // where the piece starts on screen, decorations go under all of it.
+    let left_x = if right_to_left { x - width } else { x };
+    // render decoration
+    if markup.style.contains(MarkupStyle::STRIKETHROUGH) {
+        graphics_context.render_filled_rectangle(left_x,
+                                                 piece_y + (font_size as f32 / 1.8),
+                                                 width,
+                                                 font_size as f32 / 10.0,
+                                                 color);
+    }
+    if markup.style.contains(MarkupStyle::UNDERLINED) {
+        graphics_context.render_filled_rectangle(left_x,
+                                                 piece_y + (font_size as f32),
+                                                 width,
+                                                 font_size as f32 / 13.0,
+                                                 color);
+    }
+    if let Some(target) = &markup.link {
+        graphics_context.add_clickable_region(left_x, piece_y,
+                                              width, height,
+                                              target);
+    }
+    width
+}
+
+// Spans can change the size or font, but everything sits on one baseline, so a line
+// is as tall as its tallest piece above and below it. Returns (ascent, height).
+fn line_metrics(graphics_context: &mut SDL2GraphicsContext, pieces: &[LinePiece], line_font: &str, font_size: u16) -> (f32, f32) {
+    let ascent = graphics_context.font_ascent(line_font, font_size);
+    let descent = graphics_context.font_height(line_font, font_size) as f32 - ascent;
+    let line_ascent = pieces.iter().map(|piece| piece.ascent).fold(ascent, f32::max);
+    let line_descent = pieces.iter().map(|piece| piece.height - piece.ascent).fold(descent, f32::max);
+    (line_ascent, line_ascent + line_descent)
+}
+
+// What markup is drawn with where its spans don't say otherwise.
+struct MarkupBlockStyle<'a> {
+    font_size: u16,
+    color: Color,
+    font: (Option<&'a FontFamily>, &'a str),
+    fonts: &'a SlideFonts,
+}
+
+// Lays markup out left to right from x in lines no wider than max_width, only
+// breaking where there's a space, and draws it. Returns how tall it came out.
+fn render_wrapped_markup(graphics_context: &mut SDL2GraphicsContext,
+                         markups: &[Markup],
+                         x: f32,
+                         y: f32,
+                         max_width: f32,
+                         block_style: &MarkupBlockStyle) -> f32 {
+    let MarkupBlockStyle { font_size, color, font, fonts } = *block_style;
+    // a word can be more than one piece, like *bold*ish.
+    let mut lines : Vec<Vec<LinePiece>> = vec![Vec::new()];
+    let mut line_width = 0.0;
+    let mut word : Vec<LinePiece> = Vec::new();
+    let mut word_width = 0.0;
+
+    for markup in markups {
+        for text in markup.get_text_content().split_inclusive(' ') {
+            let piece = line_piece(graphics_context, markup, text, font, font_size, fonts);
+            let width = line_piece_width(graphics_context, &piece, text, &fonts.fallbacks);
+            word_width += width;
+            word.push(piece);
+
+            if text.ends_with(' ') {
+                // trailing spaces can hang off the end of a line.
+                let space_width = width - line_piece_width(graphics_context, word.last().unwrap(), text.trim_end(), &fonts.fallbacks);
+                let ink_width = word_width - space_width;
+                if !lines.last().unwrap().is_empty() && line_width + ink_width > max_width {
+                    lines.push(Vec::new());
+                    line_width = 0.0;
+                }
+                line_width += word_width;
+                word_width = 0.0;
+                lines.last_mut().unwrap().append(&mut word);
+            }
+        }
+    }
+    if !lines.last().unwrap().is_empty() && line_width + word_width > max_width {
+        lines.push(Vec::new());
+    }
+    lines.last_mut().unwrap().append(&mut word);
+
+    let mut line_y = y;
+    for line in &lines {
+        let (line_ascent, line_height) = line_metrics(graphics_context, line, font.1, font_size);
+        let piece_line = PieceLine { y: line_y, ascent: line_ascent, right_to_left: false, color, fallback_fonts: &fonts.fallbacks };
+        let mut piece_x = x;
+        for piece in line {
+            piece_x += render_line_piece(graphics_context, piece, piece_x, &piece_line);
+        }
+        line_y += line_height;
+    }
+    line_y - y
+}
+
+// Table columns are as wide as their widest cell. Measured the same way the
+// pieces are drawn, so bold cells and fallback fonts line up too.
+fn cell_width(graphics_context: &mut SDL2GraphicsContext, pieces: &[LinePiece], fallback_fonts: &[String]) -> f32 {
+    pieces.iter()
+        .map(|piece| line_piece_width(graphics_context, piece, piece.text, fallback_fonts))
+        .sum()
+}
 
 impl Page {
     pub fn render(&self,
                   graphics_context: &mut SDL2GraphicsContext,
-                  default_font: &str) {
-        use crate::markup::*;
+                  default_font: &str,
+                  fonts: &SlideFonts,
+                  step: usize) {
         graphics_context.render_filled_rectangle(0.0, 0.0,
                                                  graphics_context.logical_width() as f32,
                                                  graphics_context.logical_height() as f32,
@@ -87,10 +451,18 @@ impl Page {
             match element {
                 SlideElement::Text(text) => {
                     let font_size = text.font_size;
+                    let direction = text.direction.resolve(&text.text);
+                    let right_to_left = direction == TextDirection::RightToLeft;
+                    graphics_context.text_direction = direction;
+
                     cursor_x_baseline = match text.x {
                         Some(x) => x,
                         None => 0.0,
                     };
+                    // right to left lines start at the right edge, x is how far in from it.
+                    if right_to_left {
+                        cursor_x_baseline = graphics_context.logical_width() as f32 - cursor_x_baseline;
+                    }
 
                     cursor_x = cursor_x_baseline;
 
@@ -106,51 +478,256 @@ impl Page {
                         }
                     }
 
-                    let markup_lexer = MarkupLexer::new(&text.text);
-                    let drawn_font =
-                        if let Some(font) = &text.font_name {
-                            graphics_context.add_font(font)
-                        } else {
-                            default_font 
-                        };
+                    let font = line_font(graphics_context, text.font_name.as_ref(), default_font, fonts);
 
-                    let height = graphics_context.text_dimensions(drawn_font, &text.text, font_size).1;
+                    let height = graphics_context.font_height(font.1, font_size);
                     if last_font_size == 0 { last_font_size = height as u16; }
                     cursor_y += last_font_size as f32 * text.line_breaks as f32;
 
-                    for markup in markup_lexer {
-                        let text_content = markup.get_text_content();
-                        let width = graphics_context.render_static_text(drawn_font,
-                                                                        cursor_x, cursor_y,
-                                                                        text_content,
-                                                                        font_size,
-                                                                        text.color,
-                                                                        markup.get_text_drawing_style()) / graphics_context.camera.scale;
-                        // render decoration
-                        match markup {
-                            Markup::Strikethrough(_) => {
-                                graphics_context.render_filled_rectangle(cursor_x,
-                                                                         cursor_y + (font_size as f32 / 1.8),
-                                                                         width as f32,
-                                                                         font_size as f32 / 10.0,
-                                                                         text.color);
-                            }
-                            Markup::Underlined(_) => {
-                                graphics_context.render_filled_rectangle(cursor_x,
-                                                                         cursor_y + (font_size as f32),
-                                                                         width as f32,
-                                                                         font_size as f32 / 13.0,
-                                                                         text.color);
-                            }
-                            _ => {},
+                    let pieces : Vec<LinePiece> = text.markups.iter()
+                        .map(|markup| line_piece(graphics_context, markup, markup.get_text_content(), font, font_size, fonts))
+                        .collect();
+                    let (line_ascent, line_height) = line_metrics(graphics_context, &pieces, font.1, font_size);
+
+                    let line = PieceLine { y: cursor_y, ascent: line_ascent, right_to_left, color: text.color, fallback_fonts: &fonts.fallbacks };
+                    for piece in &pieces {
+                        let width = render_line_piece(graphics_context, piece, cursor_x, &line);
+                        if right_to_left {
+                            cursor_x -= width;
+                        } else {
+                            cursor_x += width;
                         }
-                        cursor_x += (width) as f32;
                     }
 
-                    cursor_y += height as f32;
+                    cursor_y += line_height;
                     last_font_size = font_size;
                     cursor_x = cursor_x_baseline;
                 },
+                SlideElement::List(list) => {
+                    use sdl2::ttf::FontStyle;
+                    graphics_context.text_direction = TextDirection::LeftToRight;
+
+                    if let Some(baseline_y) = list.y {
+                        if cursor_y_baseline != Some(baseline_y) {
+                            cursor_y_baseline = Some(baseline_y);
+                            cursor_y = baseline_y;
+                        }
+                    }
+
+                    let font = line_font(graphics_context, list.font_name.as_ref(), default_font, fonts);
+                    let font_size = list.font_size(0);
+                    let height = graphics_context.font_height(font.1, font_size);
+                    if last_font_size == 0 { last_font_size = height as u16; }
+                    cursor_y += last_font_size as f32 * list.line_breaks as f32;
+
+                    // bullets go where they were typed, each level in by a bit more, and
+                    // the text after them (wrapped lines too) lines up past the bullet.
+                    let space_width = graphics_context.static_text_width(font.1, " ", font_size, FontStyle::NORMAL) / graphics_context.camera.scale;
+                    let list_x = list.x.unwrap_or(0.0) + space_width * list.indent as f32;
+                    let indent_width = font_size as f32 * 1.5;
+                    let right_edge = graphics_context.logical_width() as f32 - list_x.max(font_size as f32);
+
+                    for item in &list.items {
+                        let item_size = list.font_size(item.level);
+                        let marker_x = list_x + indent_width * item.level as f32;
+                        let text_x = marker_x + indent_width;
+                        graphics_context.render_static_text(font.1,
+                                                            marker_x, cursor_y,
+                                                            &item.marker,
+                                                            item_size,
+                                                            list.color,
+                                                            FontStyle::NORMAL);
+
+                        cursor_y += render_wrapped_markup(graphics_context,
+                                                          &item.markups,
+                                                          text_x, cursor_y,
+                                                          right_edge - text_x,
+                                                          &MarkupBlockStyle { font_size: item_size, color: list.color, font, fonts });
+                    }
+
+                    last_font_size = font_size;
+                    graphics_context.text_direction = TextDirection::Auto;
+                },
+                SlideElement::Table(table) => {
+                    graphics_context.text_direction = TextDirection::LeftToRight;
+
+                    if let Some(baseline_y) = table.y {
+                        if cursor_y_baseline != Some(baseline_y) {
+                            cursor_y_baseline = Some(baseline_y);
+                            cursor_y = baseline_y;
+                        }
+                    }
+                    cursor_x = table.x.unwrap_or(0.0);
+
+                    let font = line_font(graphics_context, table.font_name.as_ref(), default_font, fonts);
+                    let height = graphics_context.font_height(font.1, table.font_size);
+                    if last_font_size == 0 { last_font_size = height as u16; }
+                    cursor_y += last_font_size as f32 * table.line_breaks as f32;
+
+                    let rows : Vec<(bool, &Vec<Vec<Markup>>)> = table.header.iter().map(|row| (true, row))
+                        .chain(table.rows.iter().map(|row| (false, row)))
+                        .collect();
+
+                    let padding = table.font_size as f32 / 4.0;
+                    let mut column_widths = vec![0.0_f32; table.alignments.len()];
+                    let mut row_heights = Vec::with_capacity(rows.len());
+                    for (_, row) in &rows {
+                        let mut row_height = 0.0_f32;
+                        for (column, markups) in row.iter().enumerate() {
+                            let pieces : Vec<LinePiece> = markups.iter()
+                                .map(|markup| line_piece(graphics_context, markup, markup.get_text_content(), font, table.font_size, fonts))
+                                .collect();
+                            column_widths[column] = column_widths[column].max(cell_width(graphics_context, &pieces, &fonts.fallbacks) + padding * 2.0);
+                            row_height = row_height.max(line_metrics(graphics_context, &pieces, font.1, table.font_size).1 + padding * 2.0);
+                        }
+                        row_heights.push(row_height);
+                    }
+                    let table_width : f32 = column_widths.iter().sum();
+                    let table_height : f32 = row_heights.iter().sum();
+
+                    let mut row_y = cursor_y;
+                    let mut body_row = 0;
+                    for ((header, row), row_height) in rows.iter().zip(&row_heights) {
+                        if *header {
+                            graphics_context.render_filled_rectangle(cursor_x, row_y, table_width, *row_height,
+                                                                     Color { a: 60, .. table.color });
+                        } else {
+                            if table.zebra && body_row % 2 == 1 {
+                                graphics_context.render_filled_rectangle(cursor_x, row_y, table_width, *row_height,
+                                                                         Color { a: 20, .. table.color });
+                            }
+                            body_row += 1;
+                        }
+
+                        let mut cell_x = cursor_x;
+                        for ((markups, column_width), alignment) in row.iter().zip(&column_widths).zip(&table.alignments) {
+                            let pieces : Vec<LinePiece> = markups.iter()
+                                .map(|markup| line_piece(graphics_context, markup, markup.get_text_content(), font, table.font_size, fonts))
+                                .collect();
+                            let width = cell_width(graphics_context, &pieces, &fonts.fallbacks);
+                            let (line_ascent, line_height) = line_metrics(graphics_context, &pieces, font.1, table.font_size);
+                            let mut piece_x = match alignment {
+                                CellAlignment::Left => cell_x + padding,
+                                CellAlignment::Center => cell_x + (column_width - width) / 2.0,
+                                CellAlignment::Right => cell_x + column_width - padding - width,
+                            };
+                            let line = PieceLine { y: row_y + (row_height - line_height) / 2.0,
+                                                   ascent: line_ascent,
+                                                   right_to_left: false,
+                                                   color: table.color,
+                                                   fallback_fonts: &fonts.fallbacks };
+                            for piece in &pieces {
+                                piece_x += render_line_piece(graphics_context, piece, piece_x, &line);
+                            }
+                            cell_x += column_width;
+                        }
+                        row_y += row_height;
+                    }
+
+                    // borders go over the edges of every cell, so the lines all meet.
+                    if table.border_width > 0.0 {
+                        let half_border = table.border_width / 2.0;
+                        let mut line_y = cursor_y;
+                        for row_height in std::iter::once(&0.0).chain(&row_heights) {
+                            line_y += row_height;
+                            graphics_context.render_filled_rectangle(cursor_x - half_border, line_y - half_border,
+                                                                     table_width + table.border_width, table.border_width,
+                                                                     table.color);
+                        }
+                        let mut line_x = cursor_x;
+                        for column_width in std::iter::once(&0.0).chain(&column_widths) {
+                            line_x += column_width;
+                            graphics_context.render_filled_rectangle(line_x - half_border, cursor_y - half_border,
+                                                                     table.border_width, table_height + table.border_width,
+                                                                     table.color);
+                        }
+                    }
+
+                    cursor_y += table_height;
+                    last_font_size = table.font_size;
+                    graphics_context.text_direction = TextDirection::Auto;
+                },
+                // shapes go exactly where they say, and don't move the text along.
+                SlideElement::Rect(rect) => {
+                    graphics_context.render_shape(&Shape::Rect { x: rect.x, y: rect.y, w: rect.w, h: rect.h, corner_radius: rect.corner_radius },
+                                                  &rect.style);
+                },
+                SlideElement::Circle(circle) => {
+                    graphics_context.render_shape(&Shape::Circle { x: circle.x, y: circle.y, radius: circle.radius },
+                                                  &circle.style);
+                },
+                SlideElement::Line(line) | SlideElement::Arrow(line) => {
+                    let arrow = matches!(element, SlideElement::Arrow(_));
+                    graphics_context.render_shape(&Shape::Line { x1: line.x1, y1: line.y1, x2: line.x2, y2: line.y2, arrow },
+                                                  &line.style);
+                },
+                SlideElement::Code(code) => {
+                    use sdl2::ttf::FontStyle;
+                    // code reads left to right, whatever language the slide is in.
+                    graphics_context.text_direction = TextDirection::LeftToRight;
+                    let code_font = graphics_context.add_font(CODE_FONT);
+
+                    if let Some(baseline_y) = code.y {
+                        if cursor_y_baseline != Some(baseline_y) {
+                            cursor_y_baseline = Some(baseline_y);
+                            cursor_y = baseline_y;
+                        }
+                    }
+                    cursor_x = code.x.unwrap_or(0.0);
+
+                    let line_height = graphics_context.font_height(code_font, code.font_size) as f32;
+                    if last_font_size == 0 { last_font_size = line_height as u16; }
+                    cursor_y += last_font_size as f32 * code.line_breaks as f32;
+
+                    // every character is as wide as every other, so one measurement does for the box.
+                    let column_width = graphics_context.static_text_width(code_font, "0", code.font_size, FontStyle::NORMAL) / graphics_context.camera.scale;
+                    let number_columns = if code.line_numbers { code.lines.len().to_string().len() } else { 0 };
+                    let gutter_columns = if code.line_numbers { number_columns + 2 } else { 0 };
+                    let columns = code.lines.iter()
+                        .map(|line| line.iter().map(|span| span.text.chars().count()).sum::<usize>())
+                        .max()
+                        .unwrap_or(0);
+                    let padding = code.font_size as f32 / 2.0;
+                    let block_width = (gutter_columns + columns) as f32 * column_width + padding * 2.0;
+                    let block_height = code.lines.len() as f32 * line_height + padding * 2.0;
+
+                    // step 0 is before anything's highlighted.
+                    let highlighted_lines = step.checked_sub(1).and_then(|step| code.highlight_steps.get(step));
+
+                    graphics_context.render_filled_rectangle(cursor_x, cursor_y, block_width, block_height, code.theme.background);
+                    for (index, line) in code.lines.iter().enumerate() {
+                        let line_y = cursor_y + padding + index as f32 * line_height;
+                        let mut line_x = cursor_x + padding;
+                        let dimmed = highlighted_lines.map(|lines| !lines.contains(&index)).unwrap_or(false);
+                        let line_color = |color: Color| if dimmed { Color { a: color.a / 4, .. color } } else { color };
+
+                        if code.line_numbers {
+                            graphics_context.render_static_text(code_font,
+                                                                line_x, line_y,
+                                                                &format!("{:>width$}", index + 1, width = number_columns),
+                                                                code.font_size,
+                                                                line_color(code.theme.line_number),
+                                                                FontStyle::NORMAL);
+                            line_x += gutter_columns as f32 * column_width;
+                        }
+
+                        for span in line {
+                            let mut style = FontStyle::NORMAL;
+                            if span.bold { style |= FontStyle::BOLD; }
+                            if span.italic { style |= FontStyle::ITALIC; }
+                            line_x += graphics_context.render_static_text(code_font,
+                                                                          line_x, line_y,
+                                                                          &span.text,
+                                                                          code.font_size,
+                                                                          line_color(span.color),
+                                                                          style) / graphics_context.camera.scale;
+                        }
+                    }
+
+                    cursor_y += block_height;
+                    last_font_size = code.font_size;
+                    graphics_context.text_direction = TextDirection::Auto;
+                },
                 SlideElement::Image(image) => {
                     if let Ok(texture) = graphics_context.add_image(&image.location) {
                         let image_dimensions = graphics_context.image_dimensions(texture).unwrap_or((0, 0));
@@ -197,27 +774,105 @@ impl Page {
                 },
             }
         }
+
+        graphics_context.text_direction = TextDirection::Auto;
     }
 }
 
 impl Default for Page {
     fn default() -> Page {
         Page {
+            id: None,
             transition: None,
             background_color: COLOR_WHITE,
-            elements: Vec::new()
+            elements: Vec::new(),
+            notes: Vec::new(),
+            source_hash: 0,
         }
     }
 }
 
+// A font with its real bold and italic faces, so we only fake them (see
+// rasterize_glyphs) when a face is missing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontFamily {
+    pub regular: String,
+    pub bold: Option<String>,
+    pub italic: Option<String>,
+    pub bold_italic: Option<String>,
+}
+
+impl FontFamily {
+    // The face to draw with, and whatever styling is still left to fake on top of it.
+    pub fn face_for_style(&self, style: sdl2::ttf::FontStyle) -> (&str, sdl2::ttf::FontStyle) {
+        use sdl2::ttf::FontStyle;
+        let decorations = style - (FontStyle::BOLD | FontStyle::ITALIC);
+
+        let candidates : &[(&Option<String>, FontStyle)] =
+            match (style.contains(FontStyle::BOLD), style.contains(FontStyle::ITALIC)) {
+                (true, true) => &[(&self.bold_italic, FontStyle::NORMAL),
+                                  (&self.bold, FontStyle::ITALIC),
+                                  (&self.italic, FontStyle::BOLD)],
+                (true, false) => &[(&self.bold, FontStyle::NORMAL)],
+                (false, true) => &[(&self.italic, FontStyle::NORMAL)],
+                (false, false) => &[],
+            };
+
+        candidates.iter()
+            .find_map(|(face, faked_style)| face.as_ref().map(|face| (face.as_str(), *faked_style | decorations)))
+            .unwrap_or((&self.regular, style))
+    }
+
+    pub fn faces(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(&self.regular)
+            .chain(self.bold.iter())
+            .chain(self.italic.iter())
+            .chain(self.bold_italic.iter())
+            .map(|face| face.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SlideFonts {
+    // $font-fallback, for characters missing from the page's font.
+    pub fallbacks: Vec<String>,
+    // $font-family, by name.
+    pub families: HashMap<String, FontFamily>,
+}
+
+impl Default for SlideFonts {
+    // The bundled font has real faces, so it gets a family without asking.
+    fn default() -> SlideFonts {
+        let mut families = HashMap::new();
+        families.insert(String::from("libre-baskerville"),
+                        FontFamily {
+                            regular: String::from(crate::settings::DEFAULT_FONT),
+                            bold: Some(String::from("data/fonts/libre-baskerville/LibreBaskerville-Bold.ttf")),
+                            italic: Some(String::from("data/fonts/libre-baskerville/LibreBaskerville-Italic.ttf")),
+                            bold_italic: None,
+                        });
+        SlideFonts { fallbacks: Vec::new(), families }
+    }
+}
+
+impl SlideFonts {
+    // $font can name a family, or be the path of one's regular face.
+    pub fn family(&self, font: &str) -> Option<&FontFamily> {
+        self.families.get(font)
+            .or_else(|| self.families.values().find(|family| family.regular == font))
+    }
+}
+
 pub struct Slide {
     pub file_name : String, // owned string for hot reloading.
-    pub last_modified_time: std::time::SystemTime,
 
     pub pages : Vec<Page>,
     pub current_page : isize,
+    // how far through the current page's code highlights we are, 0 is none yet.
+    pub current_step : usize,
 
     pub resolution : (u32, u32),
+    pub fonts: SlideFonts,
 }
 impl Default for Slide {
     fn default() -> Slide {
@@ -225,33 +880,33 @@ impl Default for Slide {
             file_name: String::new(),
             pages: Vec::new(),
             current_page: isize::default(),
-            last_modified_time: std::time::SystemTime::now(),// eh...
+            current_step: 0,
             resolution: (1280, 720),
+            fonts: SlideFonts::default(),
         }
     }
 }
 
 impl Slide {
-    pub fn new_from_file(file_name: &str) -> Option<Slide> {
-        match load_file(file_name) {
-            Ok(file_source) => {
-                use crate::slide_parser::compile_slide;
-                let slideshow_source = remove_comments_from_source(&file_source);
-                let compiled_slide = compile_slide(&slideshow_source);
-
-                if let Ok(slide) = compiled_slide {
-                    Some(Slide {
-                            file_name: file_name.to_owned(),
-                            current_page: 0,
-                            last_modified_time: file_last_modified_time(file_name),
-                            .. slide
-                        })
-                } else {
-                    None
-                }
+    pub fn compile_from_file(file_name: &str) -> Result<Slide, SlideCompileError> {
+        use crate::slide_parser::compile_slide;
+        let file_source = load_file(file_name)
+            .map_err(|message| SlideCompileError { line: None, message })?;
+        let slideshow_source = remove_comments_from_source(&file_source);
+
+        match compile_slide(&slideshow_source) {
+            Ok(slide) => {
+                Ok(Slide {
+                    file_name: file_name.to_owned(),
+                    current_page: 0,
+                    .. slide
+                })
             },
-            Err(_) => {
-                None
+            Err(error) => {
+                Err(SlideCompileError {
+                    line: error.line.map(|line| line_number_before_removing_comments(&file_source, line)),
+                    .. error
+                })
             }
         }
     }
@@ -265,7 +920,9 @@ impl Slide {
 
         if let Some(selected_page) = self.get(page) {
             graphics_context.use_viewport_letterbox();
-            selected_page.render(graphics_context, default_font);
+            // only the page being presented is partway through its steps.
+            let step = if page as isize == self.current_page { self.current_step } else { 0 };
+            selected_page.render(graphics_context, default_font, &self.fonts, step);
         } else {
             graphics_context.clear_color(Color::new(10, 10, 16, 255));
             graphics_context.use_viewport_default();
@@ -281,36 +938,95 @@ impl Slide {
         }
     }
 
-    // handle errors more explictly...
-    #[allow(dead_code)]
-    pub fn file_last_modified_time(&self) -> std::time::SystemTime {
-        file_last_modified_time(&self.file_name)
+    // Everything the slideshow needs from disk, so we know what to watch.
+    pub fn referenced_files(&self) -> Vec<&str> {
+        let mut files = vec![self.file_name.as_str()];
+        files.extend(self.fonts.fallbacks.iter().map(|font| font.as_str()));
+        files.extend(self.fonts.families.values().flat_map(|family| family.faces()));
+        for page in &self.pages {
+            for element in &page.elements {
+                match element {
+                    SlideElement::Text(text) => {
+                        if let Some(font) = &text.font_name {
+                            files.push(font);
+                        }
+                    },
+                    SlideElement::Image(image) => {
+                        files.push(&image.location);
+                    },
+                    SlideElement::List(list) => {
+                        if let Some(font) = &list.font_name {
+                            files.push(font);
+                        }
+                    },
+                    SlideElement::Table(table) => {
+                        if let Some(font) = &table.font_name {
+                            files.push(font);
+                        }
+                    },
+                    SlideElement::Code(_) | SlideElement::Rect(_) | SlideElement::Circle(_) |
+                    SlideElement::Line(_) | SlideElement::Arrow(_) => {},
+                }
+            }
+        }
+        files
     }
 
-    pub fn reload(&mut self) -> Result<(), ()> {
-        let previous_page_count = self.len();
-        let previous_current_page = self.current_page();
+    // The file watcher decides when this is worth doing, so this always recompiles.
+    // If it doesn't compile, we keep what we had.
+    pub fn reload(&mut self) -> Result<(), SlideCompileError> {
+        let slide = Slide::compile_from_file(&self.file_name)?;
+        let current_page = self.page_after_reload(&slide);
+        let current_step = if current_page as isize == self.current_page { self.current_step } else { 0 };
 
-        let new_slide = Slide::new_from_file(&self.file_name);
-        if let Some(slide) = new_slide {
-            if slide.last_modified_time > self.last_modified_time {
-                if previous_page_count == slide.len() {
-                    *self = slide;
-                    self.current_page = previous_current_page;
-                } else {
-                    *self = slide;
-                    self.current_page = 0;
-                }
+        *self = slide;
+        self.current_page = current_page as isize;
+        self.current_step = current_step.min(self.get_current_page().map(|page| page.steps()).unwrap_or(0));
+        Ok(())
+    }
+
+    // Where to be in the reloaded slideshow. If exactly one page is new or was
+    // edited, that's what the author is working on, so go look at it. Otherwise
+    // try to stay on the same page even if pages were added or removed before it,
+    // first by its id, then by its contents, and only then by its number.
+    fn page_after_reload(&self, reloaded: &Slide) -> usize {
+        let last_page = reloaded.len().saturating_sub(1);
+
+        let mut edited_pages = reloaded.pages.iter().enumerate().filter(
+            |(_, page)| {
+                !self.pages.iter().any(|old_page| old_page.source_hash == page.source_hash)
+            });
+        if let (Some((edited_page, _)), None) = (edited_pages.next(), edited_pages.next()) {
+            return edited_page;
+        }
+
+        let current_page = self.current_page().max(0) as usize;
+        let old_page = match self.get(current_page) {
+            Some(old_page) => old_page,
+            None => return current_page.min(last_page),
+        };
+
+        if let Some(id) = &old_page.id {
+            if let Some(page) = reloaded.pages.iter().position(|page| page.id.as_ref() == Some(id)) {
+                return page;
             }
-            Ok(())
-        } else {
-            Err(())
         }
+
+        // identical pages happen (section dividers), so take the closest one.
+        let same_contents = reloaded.pages.iter().enumerate()
+            .filter(|(_, page)| page.source_hash == old_page.source_hash)
+            .map(|(index, _)| index)
+            .min_by_key(|index| (*index as isize - current_page as isize).abs());
+        if let Some(page) = same_contents {
+            return page;
+        }
+
+        current_page.min(last_page)
     }
 
-    pub fn finish_transition(&mut self) {
-        if let Some(_current_page) = &mut self.get_current_page() {
-            if let Some(transition) = &mut self.get_current_page_mut().unwrap().transition {
+    pub fn finish_transition(&mut self, page: usize) {
+        if let Some(page) = self.get_mut(page) {
+            if let Some(transition) = &mut page.transition {
                 transition.time = 0.0;
             }
         }
@@ -327,6 +1043,22 @@ impl Slide {
         self.current_page
     }
 
+    // Links can name a page by id, or just by its number.
+    pub fn find_page_by_link(&self, target: &str) -> Option<usize> {
+        let by_id = self.pages.iter().position(
+            |page| {
+                page.id.as_ref().map(|id| id == target).unwrap_or(false)
+            });
+
+        by_id.or_else(
+            || {
+                match target.parse::<usize>() {
+                    Ok(page_number) if page_number >= 1 && page_number <= self.len() => Some(page_number - 1),
+                    _ => None,
+                }
+            })
+    }
+
     pub fn get_current_page(&self) -> Option<&Page> {
         self.get(self.current_page as usize)
     }
@@ -334,25 +1066,92 @@ impl Slide {
         self.pages.get(index)
     }
 
-    pub fn get_current_page_mut(&mut self) -> Option<&mut Page> {
-        self.get_mut(self.current_page as usize)
-    }
     pub fn get_mut(&mut self, index: usize) -> Option<&mut Page> {
         self.pages.get_mut(index)
     }
 
+    // False when there are no steps left, and it's time for the next page.
+    pub fn next_step(&mut self) -> bool {
+        let steps = self.get_current_page().map(|page| page.steps()).unwrap_or(0);
+        if self.current_step < steps {
+            self.current_step += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn previous_step(&mut self) -> bool {
+        if self.current_step > 0 {
+            self.current_step -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn next_page(&mut self) -> isize {
+        self.current_step = 0;
         let desired_next_page = self.current_page + 1;
         self.current_page += 1;
         self.current_page = clamp(self.current_page as i32, 0, self.len() as i32 - 1) as isize;
         desired_next_page
     }
 
+    // Going back lands where the page was left, with all of its highlights stepped through.
     pub fn previous_page(&mut self) -> isize {
         let desired_next_page = self.current_page - 1;
         self.current_page -= 1;
         self.current_page = clamp(self.current_page as i32, 0, self.len() as i32 - 1) as isize;
+        self.current_step =
+            if self.current_page == desired_next_page {
+                self.get_current_page().map(|page| page.steps()).unwrap_or(0)
+            } else {
+                0
+            };
         desired_next_page
     }
+
+    pub fn set_current_page(&mut self, page: isize) -> isize {
+        let last_page = (self.len() as isize - 1).max(0);
+        self.current_step = 0;
+        self.current_page = clamp(page, 0, last_page);
+        self.current_page
+    }
+
+    pub fn first_page(&mut self) -> isize {
+        self.set_current_page(0)
+    }
+
+    pub fn last_page(&mut self) -> isize {
+        self.set_current_page(self.len() as isize - 1)
+    }
 }
 
+#[cfg(test)]
+#[test]
+fn demo_deck_compiles() {
+    if let Err(error) = Slide::compile_from_file("test.slide") {
+        panic!("test.slide didn't compile: {}", error);
+    }
+}
+#[test]
+fn previous_page_lands_on_last_step() {
+    use crate::slide_parser::{parse_page, SlideSettingsContext};
+
+    let mut context = SlideSettingsContext::default();
+    let stepped_page = parse_page(&mut context, vec!["$code:c highlight=1,2-3", "a;", "b;", "c;", "$end_code"], 1).unwrap();
+    let plain_page = parse_page(&mut context, vec!["text"], 1).unwrap();
+    let mut slide = Slide { pages: vec![stepped_page, plain_page], ..Slide::default() };
+
+    assert_eq!(1, slide.next_page());
+    assert!(!slide.previous_step());
+    assert_eq!(0, slide.previous_page());
+    assert_eq!(2, slide.current_step);
+    assert!(slide.previous_step());
+    assert_eq!(1, slide.current_step);
+
+    // there's nowhere to go back to, so the page starts over.
+    assert_eq!(-1, slide.previous_page());
+    assert_eq!((0, 0), (slide.current_page, slide.current_step));
+}
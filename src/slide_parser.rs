@@ -3,6 +3,32 @@ use crate::color::COLOR_WHITE;
 use crate::color::COLOR_BLACK;
 use crate::slide::*;
 use crate::utility::*;
+use crate::markup::{Markup, MarkupStyle, SpanStyle, parse_span_style, parse_markup};
+use crate::code_highlighting::{CodeHighlighter, default_code_theme};
+use std::collections::HashMap;
+
+// line is counted from 1, and is None when there's no one line to blame
+// (like the file not being readable at all).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlideCompileError {
+    pub line: Option<usize>,
+    pub message: &'static str,
+}
+
+impl SlideCompileError {
+    pub fn at_line(line: usize, message: &'static str) -> SlideCompileError {
+        SlideCompileError { line: Some(line), message }
+    }
+}
+
+impl std::fmt::Display for SlideCompileError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(formatter, "line {}: {}", line, self.message),
+            None => write!(formatter, "{}", self.message),
+        }
+    }
+}
 
 pub struct SlideSettingsContext {
     pub current_line: u32,
@@ -13,6 +39,21 @@ pub struct SlideSettingsContext {
     pub current_element_color: Color,
     pub current_font_size: u16,
     pub current_font_path: Option<String>,
+    pub current_direction: TextDirection,
+    // None picks one that suits the background.
+    pub current_code_theme: Option<String>,
+    // by list level, going round again for deeper levels.
+    pub current_bullets: Vec<String>,
+    // by list level, empty shrinks each level from the font size.
+    pub current_list_font_sizes: Vec<u16>,
+    pub fonts: SlideFonts,
+    // $span-style, for {@name|text}.
+    pub span_styles: HashMap<String, SpanStyle>,
+}
+
+// Ones the default font has.
+fn default_bullets() -> Vec<String> {
+    vec![String::from("•"), String::from("–"), String::from("·")]
 }
 
 impl Default for SlideSettingsContext {
@@ -25,6 +66,12 @@ impl Default for SlideSettingsContext {
             current_element_color: COLOR_BLACK,
             current_font_size: 48,
             current_font_path: None,
+            current_direction: TextDirection::Auto,
+            current_code_theme: None,
+            current_bullets: default_bullets(),
+            current_list_font_sizes: Vec::new(),
+            fonts: SlideFonts::default(),
+            span_styles: HashMap::new(),
         }
     }
 }
@@ -59,25 +106,105 @@ pub enum Command <'a> {
     ResetPosition,
     ResetFont, // TODO think of better thing.
     SetFont(&'a str),
+    SetFontFallbacks(Vec<&'a str>),
     SetBackgroundColor(Color),
     SetColor(Color),
     SetFontSize(u16),
+    SetDirection(TextDirection),
+    SetCodeTheme(Option<&'a str>),
+    SetBullets(Vec<&'a str>),
+    SetListFontSizes(Vec<u16>),
     SetVirtualResolution(u32, u32),
     SetTransition(SlideTransition),
     SetPosition(Option<f32>, Option<f32>),
     InsertImage(bool, &'a str, Option<f32>, Option<f32>),
 }
 
+const NO_SUCH_FONT : &str = "No font file, font-family or installed font by that name.";
+
+// A font file, a $font-family from this deck, or the name of an installed font.
+// Installed families become deck families, so their bold and italic faces get used.
+fn resolve_font(context: &mut SlideSettingsContext, font_name: &str) -> Result<String, &'static str> {
+    use crate::system_fonts::SystemFonts;
+
+    if context.fonts.families.contains_key(font_name) || std::path::Path::new(font_name).is_file() {
+        return Ok(font_name.to_owned());
+    }
+
+    if let Some(family) = SystemFonts::get().find_family(font_name) {
+        context.fonts.families.insert(font_name.to_owned(), family);
+        return Ok(font_name.to_owned());
+    }
+
+    resolve_font_file(font_name)
+}
+
+// Same, but for places that want one file, like fallbacks.
+fn resolve_font_file(font_name: &str) -> Result<String, &'static str> {
+    use crate::system_fonts::SystemFonts;
+
+    if std::path::Path::new(font_name).is_file() {
+        return Ok(font_name.to_owned());
+    }
+
+    let system_fonts = SystemFonts::get();
+    system_fonts.find_face(font_name)
+        .map(|path| path.to_string_lossy().into_owned())
+        .or_else(|| system_fonts.find_family(font_name).map(|family| family.regular))
+        .ok_or(NO_SUCH_FONT)
+}
+
+// Spans name fonts the same way $font does, but the name is all the renderer
+// gets, so anything found somewhere else is remembered under that name.
+fn resolve_span_font(context: &mut SlideSettingsContext, font_name: &str) -> Result<(), &'static str> {
+    let resolved = resolve_font(context, font_name)?;
+    if resolved != font_name {
+        context.fonts.families.insert(font_name.to_owned(), FontFamily { regular: resolved, .. FontFamily::default() });
+    }
+    Ok(())
+}
+
 // TODO!
 pub fn execute_command(context: &mut SlideSettingsContext, command: Command) -> Result<(), &'static str> {
     match command {
         Command::SetColor(color) => {context.current_element_color = color;},
         Command::SetBackgroundColor(color) => {context.current_background_color = color;},
         Command::SetFontSize(font_size) => {context.current_font_size = font_size;}
+        Command::SetDirection(direction) => {context.current_direction = direction;},
+        Command::SetCodeTheme(theme_name) => {context.current_code_theme = theme_name.map(|theme_name| theme_name.to_owned());},
+        Command::SetBullets(bullets) => {
+            context.current_bullets =
+                if bullets.is_empty() {
+                    default_bullets()
+                } else {
+                    bullets.iter().map(|bullet| (*bullet).to_owned()).collect()
+                };
+        },
+        Command::SetListFontSizes(font_sizes) => {context.current_list_font_sizes = font_sizes;},
         // the compiled slide should not depend on the source...
-        Command::SetFont(font_name) => {context.current_font_path = Some(font_name.to_owned());},
+        Command::SetFont(font_name) => {
+            // a missing font shouldn't take the whole deck down, the text is still readable.
+            context.current_font_path = match resolve_font(context, font_name) {
+                Ok(font_path) => Some(font_path),
+                Err(error) => {
+                    println!("warning: \"{}\": {} Using the default font.", font_name, error);
+                    None
+                }
+            };
+        },
         Command::ResetPosition => {context.set_position(None, None);}
         Command::ResetFont => {context.current_font_path = None;},
+        Command::SetFontFallbacks(font_names) => {
+            context.fonts.fallbacks = font_names.iter()
+                .filter_map(|font_name| match resolve_font_file(font_name) {
+                    Ok(font_path) => Some(font_path),
+                    Err(error) => {
+                        println!("warning: \"{}\": {} Leaving it out of the fallbacks.", font_name, error);
+                        None
+                    }
+                })
+                .collect();
+        },
         Command::SetPosition(x, y) => {context.set_position(x,y);},
         _ => {
             // return Err(format!("{:?} is an unknown command or not handled here", command));
@@ -113,46 +240,493 @@ pub fn execute_command_on_page(context: &mut SlideSettingsContext, command: Comm
 
 // This will call parse command and execute command
 pub fn handle_command(context: &mut SlideSettingsContext, command: SlideLineCommand) -> Result<(), &'static str> {
-    let command = parse_single_command(command);
-    if let Ok(command) = command {
-        execute_command(context, command)?;
-    } else {
-        return Err("Was not able to parse command?");
-    }
-
-    Ok(())
+    let command = parse_single_command(command)?;
+    execute_command(context, command)
 }
 
 pub fn handle_command_with_page(context: &mut SlideSettingsContext,
                                 command: SlideLineCommand,
                                 page: &mut Page) -> Result<(), &'static str> {
-    let command = parse_single_command(command);
-    if let Ok(command) = command {
-        execute_command_on_page(context, command, page)?;
-    } else {
-        return Err("Was not able to parse command?");
+    let command = parse_single_command(command)?;
+    execute_command_on_page(context, command, page)
+}
+
+// $code:language line_numbers highlight=3-5,8
+struct CodeBlockBuilder {
+    line: usize,
+    language: String,
+    line_numbers: bool,
+    highlight_steps: Vec<Vec<usize>>,
+    lines: Vec<String>,
+}
+
+// 3-5,8 is lines 3 to 5, then line 8. Lines count from 1 like the line numbers do,
+// what comes back counts from 0.
+pub fn parse_highlight_steps(source: &str) -> Result<Vec<Vec<usize>>, &'static str> {
+    const BAD_HIGHLIGHT : &'static str = "Expected highlight=lines like highlight=3-5,8";
+    let line_number = |line: &str| {
+        match line.trim().parse::<usize>() {
+            Ok(line) if line >= 1 => Ok(line - 1),
+            _ => Err(BAD_HIGHLIGHT),
+        }
+    };
+
+    source.split(',')
+        .map(|step| {
+            let mut range = step.splitn(2, '-');
+            let first = line_number(range.next().unwrap_or(""))?;
+            let last = match range.next() {
+                Some(last) => line_number(last)?,
+                None => first,
+            };
+            if last < first {
+                return Err(BAD_HIGHLIGHT);
+            }
+            Ok((first..=last).collect())
+        })
+        .collect()
+}
+
+impl CodeBlockBuilder {
+    fn new(commands: &[SlideLineCommand], line: usize) -> Result<CodeBlockBuilder, &'static str> {
+        let language = commands[0].args.first().map(|language| language.to_lowercase()).unwrap_or_default();
+        if !CodeHighlighter::get().has_language(&language) {
+            return Err("No syntax highlighting for that language.");
+        }
+
+        let mut code = CodeBlockBuilder { line, language, line_numbers: false, highlight_steps: Vec::new(), lines: Vec::new() };
+        for option in &commands[1..] {
+            match option.name {
+                "line_numbers" => { code.line_numbers = true; },
+                _ if option.name.starts_with("highlight=") => {
+                    code.highlight_steps = parse_highlight_steps(&option.name["highlight=".len()..])?;
+                },
+                _ => { return Err("Unknown code option, expected line_numbers or highlight=."); }
+            }
+        }
+        Ok(code)
     }
 
-    Ok(())
+    fn build(&self, context: &SlideSettingsContext, line_breaks: u32) -> Result<CodeElement, &'static str> {
+        if self.highlight_steps.iter().flatten().any(|line| *line >= self.lines.len()) {
+            return Err("Highlighted line is past the end of the code.");
+        }
+
+        let highlighter = CodeHighlighter::get();
+        let theme_name = context.current_code_theme.as_deref()
+            .unwrap_or_else(|| default_code_theme(context.current_background_color));
+
+        Ok(CodeElement {
+            x: context.x(),
+            y: context.y(),
+            line_breaks,
+            lines: highlighter.highlight(&self.language, &self.lines, theme_name)?,
+            font_size: context.current_font_size,
+            line_numbers: self.line_numbers,
+            theme: highlighter.theme(theme_name)?,
+            highlight_steps: self.highlight_steps.clone(),
+        })
+    }
+}
+
+// - item or 1. item, returns how far in it is (tabs are 4), its number and its text.
+pub fn parse_list_item(line: &str) -> Option<(usize, Option<u32>, &str)> {
+    let text = line.trim_start_matches([' ', '\t']);
+    let indent = line[..line.len() - text.len()].chars()
+        .map(|character| if character == '\t' { 4 } else { 1 })
+        .sum();
+
+    if let Some(item) = text.strip_prefix("- ") {
+        return Some((indent, None, item));
+    }
+
+    let digits = text.find(|character: char| !character.is_ascii_digit()).unwrap_or(text.len());
+    let item = text[digits..].strip_prefix(". ")?;
+    let number = text[..digits].parse::<u32>().ok()?;
+    Some((indent, Some(number), item))
+}
+
+// Consecutive item lines. Going in further than the last item starts a nested list,
+// coming back out goes back to whichever list was that far in.
+struct ListBuilder {
+    indents: Vec<usize>,
+    // the last number at each level, None when it was a bullet.
+    numbers: Vec<Option<u32>>,
+    element: ListElement,
+}
+
+impl ListBuilder {
+    fn new(context: &SlideSettingsContext, indent: usize, line_breaks: u32) -> ListBuilder {
+        let font_sizes =
+            if context.current_list_font_sizes.is_empty() {
+                (0..4).map(|level| (context.current_font_size as f32 * 0.85_f32.powi(level)) as u16).collect()
+            } else {
+                context.current_list_font_sizes.clone()
+            };
+
+        ListBuilder {
+            indents: vec![indent],
+            numbers: vec![None],
+            element: ListElement {
+                x: context.x(),
+                y: context.y(),
+                line_breaks,
+                indent,
+                items: Vec::new(),
+                font_sizes,
+                font_name: context.current_font_path.clone(),
+                color: context.current_element_color,
+            },
+        }
+    }
+
+    fn add(&mut self, context: &SlideSettingsContext, indent: usize, number: Option<u32>, markups: Vec<Markup>) {
+        while self.indents.len() > 1 && indent < *self.indents.last().unwrap() {
+            self.indents.pop();
+            self.numbers.pop();
+        }
+        if indent > *self.indents.last().unwrap() {
+            self.indents.push(indent);
+            self.numbers.push(None);
+        }
+        let level = self.indents.len() - 1;
+
+        // only the first number counts, the rest follow on from it.
+        let number = number.map(|number| self.numbers[level].map(|last| last + 1).unwrap_or(number));
+        self.numbers[level] = number;
+
+        let marker = match number {
+            Some(number) => format!("{}.", number),
+            None => context.current_bullets[level % context.current_bullets.len()].clone(),
+        };
+        self.element.items.push(ListItem { level, marker, markups });
+    }
+}
+
+// | a | b | c |, the pipes on the ends can be left off. Pipes in {spans} and `code`
+// stay in the cell, and so does \|.
+pub fn parse_table_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut span_depth = 0;
+    let mut inside_code = false;
+    let mut characters = line.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => {
+                match characters.next() {
+                    Some('|') => { cell.push('|'); },
+                    Some(escaped) => { cell.push('\\'); cell.push(escaped); },
+                    None => { cell.push('\\'); },
+                }
+            },
+            '`' => { inside_code = !inside_code; cell.push(character); },
+            '{' if !inside_code => { span_depth += 1; cell.push(character); },
+            '}' if !inside_code => { span_depth = std::cmp::max(span_depth, 1) - 1; cell.push(character); },
+            '|' if span_depth == 0 && !inside_code => {
+                cells.push(cell.trim().to_owned());
+                cell.clear();
+            },
+            _ => { cell.push(character); },
+        }
+    }
+    if !cell.trim().is_empty() {
+        cells.push(cell.trim().to_owned());
+    }
+    cells
+}
+
+// A |:---|:---:|---:| cell.
+fn parse_cell_alignment(cell: &str) -> Option<CellAlignment> {
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || dashes.chars().any(|character| character != '-') {
+        return None;
+    }
+    Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => CellAlignment::Center,
+        (false, true) => CellAlignment::Right,
+        _ => CellAlignment::Left,
+    })
+}
+
+// $table zebra border=2
+struct TableBuilder {
+    line: usize,
+    zebra: bool,
+    border_width: f32,
+    // from the |---| row, which makes the row above it the header.
+    alignments: Option<Vec<CellAlignment>>,
+    rows: Vec<Vec<Vec<Markup>>>,
 }
 
-pub fn parse_page(context: &mut SlideSettingsContext, page_lines: Vec<&str>) -> Result<Page, &'static str> {
+impl TableBuilder {
+    fn new(commands: &[SlideLineCommand], line: usize) -> Result<TableBuilder, &'static str> {
+        let mut table = TableBuilder { line, zebra: false, border_width: 1.0, alignments: None, rows: Vec::new() };
+        for option in &commands[1..] {
+            match option.name {
+                "zebra" => { table.zebra = true; },
+                _ if option.name.starts_with("border=") => {
+                    table.border_width = option.name["border=".len()..].parse::<f32>()
+                        .ok()
+                        .filter(|border_width| *border_width >= 0.0)
+                        .ok_or("Expected a width like border=2")?;
+                },
+                _ => { return Err("Unknown table option, expected zebra or border=."); }
+            }
+        }
+        Ok(table)
+    }
+
+    fn add_row(&mut self, context: &mut SlideSettingsContext, line: &str) -> Result<(), &'static str> {
+        let cells = parse_table_row(line);
+        let alignments : Option<Vec<CellAlignment>> = cells.iter().map(|cell| parse_cell_alignment(cell)).collect();
+        match alignments {
+            Some(alignments) if !alignments.is_empty() => {
+                if self.rows.len() != 1 || self.alignments.is_some() {
+                    return Err("The |---| row of a table goes right under its header.");
+                }
+                self.alignments = Some(alignments);
+            },
+            _ => {
+                let cells = cells.iter()
+                    .map(|cell| parse_text_markup(context, cell))
+                    .collect::<Result<_, _>>()?;
+                self.rows.push(cells);
+            }
+        }
+        Ok(())
+    }
+
+    fn build(mut self, context: &SlideSettingsContext, line_breaks: u32) -> Result<TableElement, &'static str> {
+        if self.rows.is_empty() {
+            return Err("A table needs at least one row.");
+        }
+
+        let has_header = self.alignments.is_some();
+        let mut alignments = self.alignments.take().unwrap_or_default();
+        let columns = self.rows.iter().map(|row| row.len()).chain(std::iter::once(alignments.len())).max().unwrap_or(0);
+        alignments.resize(columns, CellAlignment::Left);
+        for row in &mut self.rows {
+            row.resize(columns, Vec::new());
+        }
+        let mut header = if has_header { Some(self.rows.remove(0)) } else { None };
+        // the header is bold, on top of whatever the cell asks for.
+        for markup in header.iter_mut().flatten().flatten() {
+            markup.style |= MarkupStyle::BOLD;
+        }
+
+        Ok(TableElement {
+            x: context.x(),
+            y: context.y(),
+            line_breaks,
+            header,
+            rows: self.rows,
+            alignments,
+            font_size: context.current_font_size,
+            font_name: context.current_font_path.clone(),
+            color: context.current_element_color,
+            zebra: self.zebra,
+            border_width: self.border_width,
+        })
+    }
+}
+
+// $rect:x:y:w:h, $circle:x:y:radius, $line:x1:y1:x2:y2 and $arrow:x1:y1:x2:y2, then any of
+// fill=#color stroke=#color stroke_width=4 corner_radius=12 (that one's only for $rect).
+// A shape with no fill= or stroke= is filled in the current color, lines are only ever stroked.
+pub fn parse_shape(commands: &[SlideLineCommand], color: Color) -> Result<SlideElement, &'static str> {
+    use std::convert::TryFrom;
+
+    let (expected_numbers, wrong_numbers) = match commands[0].name {
+        "rect" => (4, "Expected $rect:x:y:w:h"),
+        "circle" => (3, "Expected $circle:x:y:radius"),
+        "line" => (4, "Expected $line:x1:y1:x2:y2"),
+        _ => (4, "Expected $arrow:x1:y1:x2:y2"),
+    };
+    let numbers = commands[0].args.iter()
+        .map(|number| number.parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .ok()
+        .filter(|numbers| numbers.len() == expected_numbers)
+        .ok_or(wrong_numbers)?;
+
+    let mut style = ShapeStyle { fill: None, stroke: None, stroke_width: 2.0 };
+    let mut corner_radius = 0.0;
+    for option in &commands[1..] {
+        let (name, value) = option.name.split_once('=').unwrap_or((option.name, ""));
+        match name {
+            "fill" | "stroke" => {
+                let option_color = Color::try_from(value).map_err(|_| "Bad color in shape, expected #rgb or #rrggbb.")?;
+                if name == "fill" { style.fill = Some(option_color); } else { style.stroke = Some(option_color); }
+            },
+            "stroke_width" => {
+                style.stroke_width = value.parse::<f32>().ok()
+                    .filter(|stroke_width| *stroke_width >= 0.0)
+                    .ok_or("Expected a width like stroke_width=4")?;
+            },
+            "corner_radius" if commands[0].name == "rect" => {
+                corner_radius = value.parse::<f32>().ok()
+                    .filter(|corner_radius| *corner_radius >= 0.0)
+                    .ok_or("Expected a radius like corner_radius=12")?;
+            },
+            _ => { return Err("Unknown shape option, expected fill=, stroke=, stroke_width= or corner_radius=."); }
+        }
+    }
+
+    Ok(match commands[0].name {
+        "rect" | "circle" => {
+            if style.fill.is_none() && style.stroke.is_none() {
+                style.fill = Some(color);
+            }
+            if commands[0].name == "rect" {
+                SlideElement::Rect(RectElement { x: numbers[0], y: numbers[1], w: numbers[2], h: numbers[3], corner_radius, style })
+            } else {
+                SlideElement::Circle(CircleElement { x: numbers[0], y: numbers[1], radius: numbers[2], style })
+            }
+        },
+        name => {
+            style.stroke = Some(style.stroke.or(style.fill).unwrap_or(color));
+            style.fill = None;
+            let line = LineElement { x1: numbers[0], y1: numbers[1], x2: numbers[2], y2: numbers[3], style };
+            if name == "line" { SlideElement::Line(line) } else { SlideElement::Arrow(line) }
+        },
+    })
+}
+
+// Parses the markup in a line of text, and finds any fonts its spans name.
+fn parse_text_markup(context: &mut SlideSettingsContext, text: &str) -> Result<Vec<Markup>, &'static str> {
+    let markups = parse_markup(text, &context.span_styles)?;
+    for font in markups.iter().filter_map(|markup| markup.font.as_ref()) {
+        resolve_span_font(context, font)?;
+    }
+    Ok(markups)
+}
+
+// first_line is the line number of page_lines[0] in the file, for error messages.
+pub fn parse_page(context: &mut SlideSettingsContext, page_lines: Vec<&str>, first_line: usize) -> Result<Page, SlideCompileError> {
     let mut new_page : Page = Page::default();
     context.current_line = 0;
     let mut current_line = 0;
+    let mut inside_notes = false;
+    let mut notes_line = first_line;
+    // same for code, which keeps its whitespace and isn't markup.
+    let mut code_block : Option<CodeBlockBuilder> = None;
+    // and lists, which go on until a line that isn't an item.
+    let mut list : Option<ListBuilder> = None;
+    let mut table : Option<TableBuilder> = None;
+
+    for (index, line) in page_lines.into_iter().enumerate() {
+        let line_number = first_line + index;
+
+        // notes are kept verbatim, so don't try to interpret anything
+        // until we find the end.
+        if inside_notes {
+            if let Some(commands) = parse_slide_command(&line) {
+                if commands[0].name == "end_notes" {
+                    inside_notes = false;
+                    continue;
+                }
+            }
+            new_page.notes.push(line.replace('\t', "    "));
+            continue;
+        }
+
+        if let Some(mut code) = code_block.take() {
+            if let Some(commands) = parse_slide_command(line) {
+                if commands[0].name == "end_code" {
+                    new_page.elements.push(
+                        SlideElement::Code(
+                            code.build(context, current_line)
+                                .map_err(|message| SlideCompileError::at_line(code.line, message))?));
+                    context.current_line = 0;
+                    current_line = 0;
+                    continue;
+                }
+            }
+            code.lines.push(line.replace('\t', "    "));
+            code_block = Some(code);
+            continue;
+        }
+
+        if let Some(mut rows) = table.take() {
+            if let Some(commands) = parse_slide_command(line) {
+                if commands[0].name == "end_table" {
+                    let table_line = rows.line;
+                    new_page.elements.push(
+                        SlideElement::Table(
+                            rows.build(context, current_line)
+                                .map_err(|message| SlideCompileError::at_line(table_line, message))?));
+                    context.current_line = 0;
+                    current_line = 0;
+                    continue;
+                }
+            }
+            if !line.trim().is_empty() {
+                rows.add_row(context, line)
+                    .map_err(|message| SlideCompileError::at_line(line_number, message))?;
+            }
+            table = Some(rows);
+            continue;
+        }
+
+        if let Some((indent, number, text)) = parse_list_item(line) {
+            let markups = parse_text_markup(context, &text.replace('\t', "    "))
+                .map_err(|message| SlideCompileError::at_line(line_number, message))?;
+            if list.is_none() {
+                list = Some(ListBuilder::new(context, indent, current_line));
+                context.current_line = 0;
+                current_line = 0;
+            }
+            if let Some(list) = &mut list {
+                list.add(context, indent, number, markups);
+            }
+            continue;
+        }
+        if let Some(list) = list.take() {
+            new_page.elements.push(SlideElement::List(list.element));
+        }
 
-    for line in page_lines {
         if let Some(commands) = parse_slide_command(&line) {
             match commands[0].name {
+                "notes" => {
+                    inside_notes = true;
+                    notes_line = line_number;
+                },
+                "code" => {
+                    code_block = Some(CodeBlockBuilder::new(&commands, line_number)
+                                      .map_err(|message| SlideCompileError::at_line(line_number, message))?);
+                },
+                "table" => {
+                    table = Some(TableBuilder::new(&commands, line_number)
+                                 .map_err(|message| SlideCompileError::at_line(line_number, message))?);
+                },
+                "rect" | "circle" | "line" | "arrow" => {
+                    new_page.elements.push(
+                        parse_shape(&commands, context.current_element_color)
+                            .map_err(|message| SlideCompileError::at_line(line_number, message))?);
+                },
+                "font-family" => {
+                    define_font_family(context, &line)
+                        .map_err(|message| SlideCompileError::at_line(line_number, message))?;
+                },
+                "span-style" => {
+                    define_span_style(context, line)
+                        .map_err(|message| SlideCompileError::at_line(line_number, message))?;
+                },
                 "transition" => {
-                    let cmd = parse_single_command(commands[0].clone());
-                    if let Command::SetTransition(transition) = cmd.unwrap() {
+                    let cmd = parse_single_command(commands[0].clone())
+                        .map_err(|message| SlideCompileError::at_line(line_number, message))?;
+                    if let Command::SetTransition(transition) = cmd {
                         new_page.transition = Some(transition);
                     }
                 },
                 _ => {
                     for command in commands {
-                        handle_command_with_page(context, command, &mut new_page)?;
+                        handle_command_with_page(context, command, &mut new_page)
+                            .map_err(|message| SlideCompileError::at_line(line_number, message))?;
                         new_page.background_color = context.current_background_color;
                     }
                 }
@@ -160,6 +734,16 @@ pub fn parse_page(context: &mut SlideSettingsContext, page_lines: Vec<&str>) ->
         } else {
             const REPLACE_TABS_WITH_N_SPACES : &'static str = "    ";
             if line.len() >= 1 {
+                let text = String::from(
+                    if let Some('$') = line.chars().next() {
+                        &line[1..]
+                    } else {
+                        &line
+                    }
+                ).replace('\t', REPLACE_TABS_WITH_N_SPACES);
+                let markups = parse_text_markup(context, &text)
+                    .map_err(|message| SlideCompileError::at_line(line_number, message))?;
+
                 new_page.elements.push(
                     SlideElement::Text(
                         TextElement{
@@ -168,16 +752,12 @@ pub fn parse_page(context: &mut SlideSettingsContext, page_lines: Vec<&str>) ->
 
                             // line_breaks: context.current_line,
                             line_breaks: current_line,
-                            text: String::from(
-                                if let Some('$') = line.chars().nth(0) {
-                                    &line[1..]
-                                } else {
-                                    &line
-                                }
-                            ).replace('\t', REPLACE_TABS_WITH_N_SPACES),
+                            text,
+                            markups,
                             font_size: context.current_font_size,
                             font_name: context.current_font_path.clone(),
-                            color: context.current_element_color
+                            color: context.current_element_color,
+                            direction: context.current_direction,
                         }));
                 context.current_line = 0;
                 current_line = 0;
@@ -188,6 +768,19 @@ pub fn parse_page(context: &mut SlideSettingsContext, page_lines: Vec<&str>) ->
         }
     }
 
+    if inside_notes {
+        return Err(SlideCompileError::at_line(notes_line, "End of page before an end notes!"));
+    }
+    if let Some(code) = code_block {
+        return Err(SlideCompileError::at_line(code.line, "End of page before an end code!"));
+    }
+    if let Some(table) = table {
+        return Err(SlideCompileError::at_line(table.line, "End of page before an end table!"));
+    }
+    if let Some(list) = list {
+        new_page.elements.push(SlideElement::List(list.element));
+    }
+
     context.set_position(None, None);
     Ok(new_page)
 }
@@ -297,6 +890,37 @@ pub fn parse_single_command<'a>(command: SlideLineCommand<'a>) -> Result<Command
                 Err("No font size specified?")
             }
         }
+        // no fonts turns fallback off again.
+        "font-fallback" => {
+            Ok(Command::SetFontFallbacks(args.copied().collect()))
+        },
+        // auto (or nothing) goes by the text itself.
+        "direction" => {
+            match args.next().map(|direction| direction.to_lowercase()).as_deref() {
+                None | Some("auto") => Ok(Command::SetDirection(TextDirection::Auto)),
+                Some("ltr") => Ok(Command::SetDirection(TextDirection::LeftToRight)),
+                Some("rtl") => Ok(Command::SetDirection(TextDirection::RightToLeft)),
+                Some(_) => Err("Direction should be ltr, rtl or auto."),
+            }
+        },
+        // one of syntect's themes, nothing goes back to picking by the background.
+        "code-theme" => {
+            match args.next() {
+                Some(theme_name) if !CodeHighlighter::get().has_theme(theme_name) => Err("No code theme by that name."),
+                theme_name => Ok(Command::SetCodeTheme(theme_name.copied())),
+            }
+        },
+        // nothing goes back to the default bullets.
+        "bullets" => {
+            Ok(Command::SetBullets(args.copied().collect()))
+        },
+        // nothing goes back to shrinking each level from the font size.
+        "list-font-sizes" => {
+            args.map(|font_size| font_size.parse::<u16>())
+                .collect::<Result<_, _>>()
+                .map(Command::SetListFontSizes)
+                .map_err(|_| "List font sizes should be numbers.")
+        },
         "reset-font" => {
             Ok(Command::ResetFont)
         },
@@ -355,6 +979,78 @@ pub fn parse_single_command<'a>(command: SlideLineCommand<'a>) -> Result<Command
     }
 }
 
+// $font-family:name regular="a.ttf" bold="b.ttf" italic="c.ttf" bold_italic="d.ttf"
+// Commands are split on spaces, which this needs, so it reads the line itself.
+pub fn parse_font_family(line: &str) -> Result<(String, FontFamily), &'static str> {
+    fn split_value(source: &str) -> Result<(&str, &str), &'static str> {
+        if let Some(quoted) = source.strip_prefix('"') {
+            let end = quoted.find('"').ok_or("Unclosed string in font-family.")?;
+            Ok((&quoted[..end], &quoted[end+1..]))
+        } else {
+            let end = source.find(|character: char| character.is_whitespace()).unwrap_or(source.len());
+            Ok((&source[..end], &source[end..]))
+        }
+    }
+
+    let source = line.trim().strip_prefix("$font-family:").ok_or("No name specified for font-family?")?;
+    let (name, mut source) = split_value(source)?;
+    if name.is_empty() {
+        return Err("No name specified for font-family?");
+    }
+
+    let mut family = FontFamily::default();
+    loop {
+        source = source.trim_start();
+        if source.is_empty() {
+            break;
+        }
+
+        let equals = source.find('=').ok_or("Expected face=\"file\" in font-family.")?;
+        let face = &source[..equals];
+        let (file, rest) = split_value(&source[equals+1..])?;
+        match face {
+            "regular" => { family.regular = file.to_owned(); },
+            "bold" => { family.bold = Some(file.to_owned()); },
+            "italic" => { family.italic = Some(file.to_owned()); },
+            "bold_italic" => { family.bold_italic = Some(file.to_owned()); },
+            _ => { return Err("Unknown face in font-family, expected regular, bold, italic or bold_italic."); }
+        }
+        source = rest;
+    }
+
+    if family.regular.is_empty() {
+        return Err("A font-family needs at least a regular face.");
+    }
+    Ok((name.to_owned(), family))
+}
+
+fn define_font_family(context: &mut SlideSettingsContext, line: &str) -> Result<(), &'static str> {
+    let (name, family) = parse_font_family(line)?;
+    context.fonts.families.insert(name, family);
+    Ok(())
+}
+
+// $span-style:name color=#e33 size=40 font="Fira Sans", used as {@name|text}.
+// Can build on styles defined before it with @other.
+pub fn parse_span_style_definition(line: &str, named_styles: &HashMap<String, SpanStyle>) -> Result<(String, SpanStyle), &'static str> {
+    let source = line.trim().strip_prefix("$span-style:").ok_or("No name specified for span-style?")?;
+    let name_end = source.find(|character: char| character.is_whitespace()).unwrap_or(source.len());
+    let name = &source[..name_end];
+    if name.is_empty() {
+        return Err("No name specified for span-style?");
+    }
+    Ok((name.to_owned(), parse_span_style(&source[name_end..], named_styles)?))
+}
+
+fn define_span_style(context: &mut SlideSettingsContext, line: &str) -> Result<(), &'static str> {
+    let (name, span_style) = parse_span_style_definition(line, &context.span_styles)?;
+    if let Some(font) = &span_style.font {
+        resolve_span_font(context, font)?;
+    }
+    context.span_styles.insert(name, span_style);
+    Ok(())
+}
+
 // aux function
 fn find_closing_command(line_iterator: &mut std::iter::Enumerate<std::str::Lines>, match_name: &str) -> Option<usize> {
     loop {
@@ -377,7 +1073,8 @@ fn find_closing_command(line_iterator: &mut std::iter::Enumerate<std::str::Lines
 pub fn parse_slide_command(line : &str) -> Option<Vec<SlideLineCommand>> {
     /*Since I still lex by char, I really only need to support string literals...*/
     let mut tokenized_first_pass : Vec<&str> = Vec::new();
-    let mut char_iterator = line.chars().enumerate();
+    // indices are bytes, so anything can go in a string, like $bullets:"•".
+    let mut char_iterator = line.char_indices();
 
     fn special_character(character: char) -> Option<char> {
         match character {
@@ -400,7 +1097,7 @@ pub fn parse_slide_command(line : &str) -> Option<Vec<SlideLineCommand>> {
                                 if let Some(end) = end {
                                     let string_literal = &line[start..(start+end)];
                                     tokenized_first_pass.push(string_literal);
-                                    for _ in 0..end { char_iterator.next(); }
+                                    for _ in 0..string_literal.chars().count() { char_iterator.next(); }
                                 }
                             },
                             ':' => {tokenized_first_pass.push(":");}
@@ -426,7 +1123,7 @@ pub fn parse_slide_command(line : &str) -> Option<Vec<SlideLineCommand>> {
                         if let Some(end) = end { 
                             let token_value = &line[start..end];
                             tokenized_first_pass.push(token_value);
-                            if let Some(':') = line.chars().nth(end) {
+                            if line[end..].starts_with(':') {
                                 tokenized_first_pass.push(":");
                             }
                         }
@@ -439,21 +1136,18 @@ pub fn parse_slide_command(line : &str) -> Option<Vec<SlideLineCommand>> {
     let mut commands : Vec<SlideLineCommand> = Vec::new();
 
     if tokenized_first_pass.len() >= 1 {
-        let mut token_iterator = tokenized_first_pass.iter();
+        let mut token_iterator = tokenized_first_pass.iter().peekable();
 
         while let Some(token) = token_iterator.next() {
             match token {
                 _ => {
                     let name = token;
                     let mut args : Vec<&str> = Vec::new();
-                    while let Some(token) = &token_iterator.next() {
-                        match **token {
-                            ":" => {
-                                if let Some(token) = &token_iterator.next() {
-                                    args.push(token);
-                                }
-                            },
-                            _ => { break; },
+                    // only peek, whatever isn't a : is the next command's name.
+                    while let Some(&&":") = token_iterator.peek() {
+                        token_iterator.next();
+                        if let Some(token) = &token_iterator.next() {
+                            args.push(token);
                         }
                     }
                     commands.push(SlideLineCommand{ name, args });
@@ -469,7 +1163,7 @@ pub fn parse_slide_command(line : &str) -> Option<Vec<SlideLineCommand>> {
     }
 }
 
-pub fn compile_slide(slide_source : &String) -> Result<Slide, &'static str> {
+pub fn compile_slide(slide_source : &str) -> Result<Slide, SlideCompileError> {
     let mut slide = Slide::default();
     let mut pages = Vec::new();
     let mut current_context = SlideSettingsContext::default();
@@ -487,23 +1181,45 @@ pub fn compile_slide(slide_source : &String) -> Result<Slide, &'static str> {
                             let end_page_index = end_page_index;
 
                             let page_source_lines : Vec<&str> = slide_source.lines().collect();
-                            let new_page                      = parse_page(&mut current_context, page_source_lines[index..end_page_index].to_vec())?;
+                            // lines are numbered from 1 in error messages.
+                            let mut new_page                  = parse_page(&mut current_context,
+                                                                           page_source_lines[index..end_page_index].to_vec(),
+                                                                           index+1)?;
+                            new_page.id = commands[0].args.iter()
+                                .find_map(|argument| argument.strip_prefix("id="))
+                                .map(|id| id.to_owned());
+                            new_page.source_hash = {
+                                use std::hash::{Hash, Hasher};
+                                // the $page line too, so changing the id counts as an edit.
+                                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                                page_source_lines[index-1..end_page_index].hash(&mut hasher);
+                                hasher.finish()
+                            };
                             pages.push(new_page);
                         } else {
-                            return Err("EOF before an end page!");
+                            return Err(SlideCompileError::at_line(index+1, "EOF before an end page!"));
                         }
                     },
+                    "font-family" => {
+                        define_font_family(&mut current_context, &line)
+                            .map_err(|message| SlideCompileError::at_line(index+1, message))?;
+                    },
+                    "span-style" => {
+                        define_span_style(&mut current_context, line)
+                            .map_err(|message| SlideCompileError::at_line(index+1, message))?;
+                    },
                     "resolution" => {
                         let cmd = parse_single_command(commands[0].clone());
-                        if let Command::SetVirtualResolution(w, h) = cmd.unwrap() {
+                        if let Ok(Command::SetVirtualResolution(w, h)) = cmd {
                             slide.resolution = (w, h);
                         } else {
-                            return Err("Unable to parse set virtual resolution command");
+                            return Err(SlideCompileError::at_line(index+1, "Unable to parse set virtual resolution command"));
                         }
                     },
                     _ => {
                         for command in commands {
-                            handle_command(&mut current_context, command)?;
+                            handle_command(&mut current_context, command)
+                                .map_err(|message| SlideCompileError::at_line(index+1, message))?;
                         }
                     },
                 }
@@ -517,5 +1233,241 @@ pub fn compile_slide(slide_source : &String) -> Result<Slide, &'static str> {
     }
 
     slide.pages = pages;
+    // fallbacks and families are for the whole deck, whichever was set last.
+    slide.fonts = current_context.fonts;
     Ok(slide)
 }
+
+#[cfg(test)]
+#[test]
+fn font_family_case() {
+    let (name, family) = parse_font_family("$font-family:serif regular=\"fonts/Serif Regular.ttf\" bold=fonts/SerifBold.ttf").unwrap();
+    assert_eq!("serif", name);
+    assert_eq!("fonts/Serif Regular.ttf", family.regular);
+    assert_eq!(Some(String::from("fonts/SerifBold.ttf")), family.bold);
+    assert_eq!(None, family.italic);
+}
+#[test]
+fn font_family_needs_regular_face() {
+    assert!(parse_font_family("$font-family:serif bold=\"b.ttf\"").is_err());
+    assert!(parse_font_family("$font-family:serif heavy=\"b.ttf\"").is_err());
+}
+#[test]
+fn commands_after_arguments_are_kept() {
+    // the token after an argument list used to be eaten as the end of it, so this
+    // came out as just a and c.
+    let commands = parse_slide_command("$a:1 b c:2").unwrap();
+    let names : Vec<&str> = commands.iter().map(|command| command.name).collect();
+    assert_eq!(vec!["a", "b", "c"], names);
+    assert_eq!(vec!["1"], commands[0].args);
+    assert!(commands[1].args.is_empty());
+    assert_eq!(vec!["2"], commands[2].args);
+}
+#[test]
+fn string_arguments_can_be_any_text() {
+    // string literals used to be skipped by characters but sliced by bytes, so this
+    // came out as font and a command named "\" " with "ize:3" as its argument.
+    let commands = parse_slide_command("$font:\"日本.ttf\" size:3").unwrap();
+    assert_eq!(2, commands.len());
+    assert_eq!(("font", vec!["日本.ttf"]), (commands[0].name, commands[0].args.clone()));
+    assert_eq!(("size", vec!["3"]), (commands[1].name, commands[1].args.clone()));
+}
+#[test]
+fn direction_applies_to_following_text() {
+    let mut context = SlideSettingsContext::default();
+    let page = parse_page(&mut context, vec!["before", "$direction:rtl", "after"], 1).unwrap();
+    let directions : Vec<TextDirection> = page.elements.iter()
+        .filter_map(|element| match element {
+            SlideElement::Text(text) => Some(text.direction),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(vec![TextDirection::Auto, TextDirection::RightToLeft], directions);
+    assert!(parse_page(&mut context, vec!["$direction:up"], 1).is_err());
+}
+#[test]
+fn span_styles_are_checked() {
+    let mut context = SlideSettingsContext::default();
+    let page = parse_page(&mut context,
+                          vec!["$span-style:accent color=#e33 size=40",
+                               "$span-style:loud @accent color=#f00",
+                               "a {@loud|loud} word"], 1).unwrap();
+    assert_eq!(1, page.elements.len());
+    assert_eq!(Some(40), context.span_styles["loud"].size);
+    assert_eq!(Some(Color::new(255, 0, 0, 255)), context.span_styles["loud"].color);
+
+    let error = parse_page(&mut context, vec!["fine", "a {@quiet|quiet} word"], 10).unwrap_err();
+    assert_eq!(Some(11), error.line);
+    assert!(parse_page(&mut context, vec!["$span-style:bad size=big"], 1).is_err());
+}
+#[test]
+fn options_after_arguments_are_kept() {
+    let commands = parse_slide_command("$code:rust line_numbers theme").unwrap();
+    let names : Vec<&str> = commands.iter().map(|command| command.name).collect();
+    assert_eq!(vec!["code", "line_numbers", "theme"], names);
+    assert_eq!(vec!["rust"], commands[0].args);
+
+    let commands = parse_slide_command("$font-fallback:\"a.ttf\":\"b.ttf\"").unwrap();
+    assert_eq!(1, commands.len());
+    assert_eq!(vec!["a.ttf", "b.ttf"], commands[0].args);
+}
+#[test]
+fn code_blocks_keep_their_whitespace() {
+    let mut context = SlideSettingsContext::default();
+    let page = parse_page(&mut context,
+                          vec!["$code:rust line_numbers",
+                               "fn main() {",
+                               "\tlet a = *b;",
+                               "",
+                               "}",
+                               "$end_code",
+                               "after"], 1).unwrap();
+    assert_eq!(2, page.elements.len());
+    match &page.elements[0] {
+        SlideElement::Code(code) => {
+            assert!(code.line_numbers);
+            let lines : Vec<String> = code.lines.iter()
+                .map(|line| line.iter().map(|span| span.text.as_str()).collect())
+                .collect();
+            assert_eq!(vec!["fn main() {", "    let a = *b;", "", "}"], lines);
+        },
+        _ => panic!("expected code"),
+    }
+
+    assert_eq!(Some(1), parse_page(&mut context, vec!["$code:klingon", "$end_code"], 1).unwrap_err().line);
+    assert_eq!(Some(2), parse_page(&mut context, vec!["text", "$code:c", "int x;"], 1).unwrap_err().line);
+}
+#[test]
+fn highlight_steps_case() {
+    assert_eq!(Ok(vec![vec![2, 3, 4], vec![7]]), parse_highlight_steps("3-5,8"));
+    assert!(parse_highlight_steps("0").is_err());
+    assert!(parse_highlight_steps("5-3").is_err());
+    assert!(parse_highlight_steps("3,,4").is_err());
+
+    let mut context = SlideSettingsContext::default();
+    let page = parse_page(&mut context, vec!["$code:c highlight=1,2-3", "a;", "b;", "c;", "$end_code"], 1).unwrap();
+    assert_eq!(2, page.steps());
+    assert!(parse_page(&mut context, vec!["$code:c highlight=4", "a;", "$end_code"], 1).is_err());
+}
+#[test]
+fn lists_nest_and_number_themselves() {
+    assert_eq!(Some((0, None, "item")), parse_list_item("- item"));
+    assert_eq!(Some((6, Some(3), "third")), parse_list_item("  \t3. third"));
+    assert_eq!(None, parse_list_item("-5 degrees"));
+    assert_eq!(None, parse_list_item("3.5 apples"));
+
+    let mut context = SlideSettingsContext::default();
+    let page = parse_page(&mut context,
+                          vec!["  - fruit",
+                               "    1. apples",
+                               "    7. pears",
+                               "      - green",
+                               "  - vegetables",
+                               "    2. leeks",
+                               "",
+                               "after"], 1).unwrap();
+    assert_eq!(2, page.elements.len());
+    match &page.elements[0] {
+        SlideElement::List(list) => {
+            assert_eq!(2, list.indent);
+            let items : Vec<(usize, &str, String)> = list.items.iter()
+                .map(|item| (item.level, item.marker.as_str(), item.markups.iter().map(|markup| markup.get_text_content()).collect()))
+                .collect();
+            assert_eq!(vec![(0, "•", String::from("fruit")),
+                            (1, "1.", String::from("apples")),
+                            (1, "2.", String::from("pears")),
+                            (2, "·", String::from("green")),
+                            (0, "•", String::from("vegetables")),
+                            (1, "2.", String::from("leeks"))], items);
+            assert!(list.font_size(1) < list.font_size(0));
+            assert_eq!(list.font_size(3), list.font_size(9));
+        },
+        _ => panic!("expected a list"),
+    }
+
+    let page = parse_page(&mut context, vec!["$bullets:\"*\"", "$list-font-sizes:30:20", "- a", "  - b"], 1).unwrap();
+    match &page.elements[0] {
+        SlideElement::List(list) => {
+            assert_eq!(vec!["*", "*"], list.items.iter().map(|item| item.marker.as_str()).collect::<Vec<_>>());
+            assert_eq!(20, list.font_size(1));
+        },
+        _ => panic!("expected a list"),
+    }
+    assert!(parse_page(&mut context, vec!["$list-font-sizes:big"], 1).is_err());
+}
+#[test]
+fn tables_have_headers_and_alignment() {
+    assert_eq!(vec!["a", "{color=#f00|b}", "`x|y`", "c|d", ""], parse_table_row("| a | {color=#f00|b} | `x|y` | c\\|d | |"));
+    assert_eq!(vec!["a", "b"], parse_table_row("a | b"));
+
+    let mut context = SlideSettingsContext::default();
+    let page = parse_page(&mut context,
+                          vec!["$table zebra border=2",
+                               "| Name | Time |",
+                               "|:-----|-----:|",
+                               "| *fast* | 1.2 |",
+                               "",
+                               "| slow | 30.5 | extra |",
+                               "$end_table",
+                               "after"], 1).unwrap();
+    assert_eq!(2, page.elements.len());
+    match &page.elements[0] {
+        SlideElement::Table(table) => {
+            assert!(table.zebra);
+            assert_eq!(2.0, table.border_width);
+            let header = table.header.as_ref().unwrap();
+            assert_eq!(vec![vec![Markup::styled("Name", MarkupStyle::BOLD)], vec![Markup::styled("Time", MarkupStyle::BOLD)], vec![]], *header);
+            assert_eq!(vec![Markup::styled("fast", MarkupStyle::BOLD)], table.rows[0][0]);
+            assert_eq!(vec![CellAlignment::Left, CellAlignment::Right, CellAlignment::Left], table.alignments);
+            assert_eq!(2, table.rows.len());
+            assert!(table.rows.iter().all(|row| row.len() == 3));
+        },
+        _ => panic!("expected a table"),
+    }
+
+    let page = parse_page(&mut context, vec!["$table", "a | b", "$end_table"], 1).unwrap();
+    match &page.elements[0] {
+        SlideElement::Table(table) => assert!(table.header.is_none()),
+        _ => panic!("expected a table"),
+    }
+
+    assert_eq!(Some(4), parse_page(&mut context, vec!["$table", "a", "b", "|---|", "$end_table"], 1).unwrap_err().line);
+    assert_eq!(Some(1), parse_page(&mut context, vec!["$table", "$end_table"], 1).unwrap_err().line);
+    assert_eq!(Some(1), parse_page(&mut context, vec!["$table", "a"], 1).unwrap_err().line);
+    assert!(parse_page(&mut context, vec!["$table stripes"], 1).is_err());
+}
+#[test]
+fn shapes_case() {
+    let red = Color::new(255, 0, 0, 255);
+    match parse_shape(&parse_slide_command("$rect:10:20:300:200 stroke=#f00 stroke_width=4 corner_radius=12").unwrap(), COLOR_BLACK).unwrap() {
+        SlideElement::Rect(rect) => {
+            assert_eq!((10.0, 20.0, 300.0, 200.0, 12.0), (rect.x, rect.y, rect.w, rect.h, rect.corner_radius));
+            assert_eq!(ShapeStyle { fill: None, stroke: Some(red), stroke_width: 4.0 }, rect.style);
+        },
+        _ => panic!("expected a rect"),
+    }
+    match parse_shape(&parse_slide_command("$circle:640:360:50").unwrap(), red).unwrap() {
+        SlideElement::Circle(circle) => assert_eq!(Some(red), circle.style.fill),
+        _ => panic!("expected a circle"),
+    }
+    match parse_shape(&parse_slide_command("$arrow:0:0:100:50 fill=#f00").unwrap(), COLOR_BLACK).unwrap() {
+        SlideElement::Arrow(arrow) => assert_eq!((None, Some(red)), (arrow.style.fill, arrow.style.stroke)),
+        _ => panic!("expected an arrow"),
+    }
+
+    assert!(parse_shape(&parse_slide_command("$rect:10:20:300").unwrap(), COLOR_BLACK).is_err());
+    assert!(parse_shape(&parse_slide_command("$line:0:0:a:b").unwrap(), COLOR_BLACK).is_err());
+    assert!(parse_shape(&parse_slide_command("$circle:1:2:3 corner_radius=4").unwrap(), COLOR_BLACK).is_err());
+    assert!(parse_shape(&parse_slide_command("$rect:1:2:3:4 fill=red").unwrap(), COLOR_BLACK).is_err());
+
+    let mut context = SlideSettingsContext::default();
+    assert_eq!(2, parse_page(&mut context, vec!["$line:0:0:10:10", "$rect:0:0:10:10"], 1).unwrap().elements.len());
+}
+#[test]
+fn notes_are_kept_verbatim() {
+    let mut context = SlideSettingsContext::default();
+    let page = parse_page(&mut context, vec!["shown", "$notes", "$not_a_command", "\tsay hi", "$end_notes"], 1).unwrap();
+    assert_eq!(1, page.elements.len());
+    assert_eq!(vec!["$not_a_command", "    say hi"], page.notes);
+    assert_eq!(Some(2), parse_page(&mut context, vec!["shown", "$notes", "never ends"], 1).unwrap_err().line);
+}
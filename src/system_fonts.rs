@@ -0,0 +1,364 @@
+/*
+    Finding installed fonts by name, so $font:"DejaVu Sans" works without knowing
+    where the distribution put it.
+
+    This is a (much) smaller fontconfig: walk the usual font directories, read the
+    family and style names out of each font's name table, and remember them in
+
+        <config directory>/slideshow/fonts.cache
+
+    so only fonts that are new or changed get opened again next time.
+*/
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::slide::FontFamily;
+
+pub const FONT_CACHE_FILE_NAME : &str = "fonts.cache";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFaceNames {
+    // what old software groups regular/bold/italic/bold italic under.
+    pub family: String,
+    pub style: String,
+    // the names people actually use, "DejaVu Sans" rather than "DejaVu Sans Light".
+    pub typographic_family: String,
+    pub typographic_style: String,
+}
+
+#[derive(Debug, Clone)]
+struct SystemFontFace {
+    path: PathBuf,
+    modified: u64,
+    names: FontFaceNames,
+}
+
+pub struct SystemFonts {
+    faces: Vec<SystemFontFace>,
+}
+
+// "DejaVu Sans", "dejavu-sans" and "DejaVuSans" should all find the same thing.
+fn normalized_name(name: &str) -> String {
+    name.chars()
+        .filter(|character| !(character.is_whitespace() || *character == '-' || *character == '_'))
+        .flat_map(|character| character.to_lowercase())
+        .collect()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes.get(offset..offset+2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset+4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_bytes_at(file: &mut std::fs::File, offset: u64, length: usize) -> Option<Vec<u8>> {
+    let mut bytes = vec![0; length];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+// Only reads the headers and the name table, CJK fonts are tens of megabytes.
+// Collections (.ttc) answer with their first font.
+pub fn read_font_face_names(path: &Path) -> Option<FontFaceNames> {
+    let mut file = std::fs::File::open(path).ok()?;
+
+    let mut font_offset = 0;
+    let header = read_bytes_at(&mut file, 0, 12)?;
+    if &header[0..4] == b"ttcf" {
+        font_offset = read_u32(&read_bytes_at(&mut file, 12, 4)?, 0)? as u64;
+    }
+
+    let offset_table = read_bytes_at(&mut file, font_offset, 12)?;
+    let table_count = read_u16(&offset_table, 4)? as usize;
+    let table_records = read_bytes_at(&mut file, font_offset + 12, table_count * 16)?;
+
+    let (name_table_offset, name_table_length) =
+        (0..table_count)
+        .map(|table| table * 16)
+        .find(|record| table_records.get(*record..record+4) == Some(b"name"))
+        .and_then(|record| Some((read_u32(&table_records, record+8)?, read_u32(&table_records, record+12)?)))?;
+    let name_table = read_bytes_at(&mut file, name_table_offset as u64, name_table_length as usize)?;
+
+    let record_count = read_u16(&name_table, 2)? as usize;
+    let strings_offset = read_u16(&name_table, 4)? as usize;
+
+    // Windows unicode names where there are any, they're the ones that are always there
+    // nowadays. English if it has it.
+    let mut names : HashMap<u16, (u32, String)> = HashMap::new();
+    for record in (0..record_count).map(|record| 6 + record * 12) {
+        let platform = read_u16(&name_table, record)?;
+        let encoding = read_u16(&name_table, record+2)?;
+        let language = read_u16(&name_table, record+4)?;
+        let name_id = read_u16(&name_table, record+6)?;
+        let length = read_u16(&name_table, record+8)? as usize;
+        let offset = strings_offset + read_u16(&name_table, record+10)? as usize;
+
+        if !(1..=2).contains(&name_id) && !(16..=17).contains(&name_id) {
+            continue;
+        }
+
+        let bytes = match name_table.get(offset..offset+length) {
+            Some(bytes) => bytes,
+            None => continue,
+        };
+
+        let (preference, name) =
+            match (platform, encoding) {
+                (3, 1) | (3, 10) | (0, _) => {
+                    let utf16 : Vec<u16> = bytes.chunks(2).filter_map(|pair| read_u16(pair, 0)).collect();
+                    (if language == 0x0409 { 3 } else { 2 }, String::from_utf16_lossy(&utf16))
+                },
+                (1, 0) => {
+                    (1, bytes.iter().map(|byte| *byte as char).collect())
+                },
+                _ => continue,
+            };
+
+        if names.get(&name_id).map(|(best, _)| preference > *best).unwrap_or(true) {
+            names.insert(name_id, (preference, name));
+        }
+    }
+
+    let family = names.get(&1)?.1.clone();
+    let style = names.get(&2).map(|(_, style)| style.clone()).unwrap_or_else(|| String::from("Regular"));
+    Some(FontFaceNames {
+        typographic_family: names.get(&16).map(|(_, name)| name.clone()).unwrap_or_else(|| family.clone()),
+        typographic_style: names.get(&17).map(|(_, name)| name.clone()).unwrap_or_else(|| style.clone()),
+        family,
+        style,
+    })
+}
+
+fn font_directories() -> Vec<PathBuf> {
+    use std::env;
+    let mut directories = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        if let Some(windows) = env::var_os("WINDIR") {
+            directories.push(PathBuf::from(windows).join("Fonts"));
+        }
+        if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
+            directories.push(PathBuf::from(local_app_data).join("Microsoft").join("Windows").join("Fonts"));
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        directories.push(PathBuf::from("/System/Library/Fonts"));
+        directories.push(PathBuf::from("/Library/Fonts"));
+        if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+            directories.push(home.join("Library").join("Fonts"));
+        }
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let data_home =
+            env::var_os("XDG_DATA_HOME")
+            .filter(|directory| !directory.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".local").join("share")));
+        if let Some(data_home) = data_home {
+            directories.push(data_home.join("fonts"));
+        }
+        if let Some(home) = &home {
+            directories.push(home.join(".fonts"));
+        }
+        directories.push(PathBuf::from("/usr/local/share/fonts"));
+        directories.push(PathBuf::from("/usr/share/fonts"));
+    }
+
+    directories
+}
+
+// Symlinked directories aren't followed, fonts.conf style loops are not my problem.
+fn find_font_files(directory: &Path, font_files: &mut Vec<(PathBuf, u64)>) {
+    if let Ok(entries) = std::fs::read_dir(directory) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false) {
+                find_font_files(&path, font_files);
+                continue;
+            }
+
+            let is_font =
+                path.extension()
+                .and_then(|extension| extension.to_str())
+                .map(|extension| ["ttf", "otf", "ttc"].contains(&extension.to_lowercase().as_str()))
+                .unwrap_or(false);
+
+            if is_font {
+                let modified =
+                    std::fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|modified| modified.as_secs())
+                    .unwrap_or(0);
+                font_files.push((path, modified));
+            }
+        }
+    }
+}
+
+fn cache_file_path() -> Option<PathBuf> {
+    crate::utility::user_config_directory().map(|directory| directory.join(FONT_CACHE_FILE_NAME))
+}
+
+impl SystemFonts {
+    // Built the first time someone asks, which is the first $font that isn't a file.
+    pub fn get() -> &'static SystemFonts {
+        static SYSTEM_FONTS : std::sync::OnceLock<SystemFonts> = std::sync::OnceLock::new();
+        SYSTEM_FONTS.get_or_init(SystemFonts::scan)
+    }
+
+    fn scan() -> SystemFonts {
+        let cached = SystemFonts::load_cache();
+        let cached_faces : HashMap<&Path, &SystemFontFace> =
+            cached.faces.iter().map(|face| (face.path.as_path(), face)).collect();
+
+        let mut font_files = Vec::new();
+        for directory in font_directories() {
+            find_font_files(&directory, &mut font_files);
+        }
+
+        let mut faces = Vec::new();
+        let mut changed = font_files.len() != cached.faces.len();
+        for (path, modified) in font_files {
+            match cached_faces.get(path.as_path()) {
+                Some(face) if face.modified == modified => {
+                    faces.push((*face).clone());
+                },
+                _ => {
+                    changed = true;
+                    if let Some(names) = read_font_face_names(&path) {
+                        faces.push(SystemFontFace { path, modified, names });
+                    }
+                }
+            }
+        }
+
+        let system_fonts = SystemFonts { faces };
+        if changed {
+            system_fonts.save_cache();
+        }
+        system_fonts
+    }
+
+    fn load_cache() -> SystemFonts {
+        use crate::utility::*;
+        let source = cache_file_path().as_ref().and_then(|path| path.to_str()).map(load_file);
+        let mut faces = Vec::new();
+
+        if let Some(Ok(source)) = source {
+            for line in source.lines() {
+                let fields : Vec<&str> = line.splitn(6, '\t').collect();
+                if let [modified, family, style, typographic_family, typographic_style, path] = fields[..] {
+                    if let Ok(modified) = modified.parse::<u64>() {
+                        faces.push(
+                            SystemFontFace {
+                                path: PathBuf::from(path),
+                                modified,
+                                names: FontFaceNames {
+                                    family: family.to_owned(),
+                                    style: style.to_owned(),
+                                    typographic_family: typographic_family.to_owned(),
+                                    typographic_style: typographic_style.to_owned(),
+                                },
+                            });
+                    }
+                }
+            }
+        }
+
+        SystemFonts { faces }
+    }
+
+    // Only a cache, losing it just means scanning again.
+    fn save_cache(&self) {
+        if let Some(path) = cache_file_path() {
+            if let Some(directory) = path.parent() {
+                if std::fs::create_dir_all(directory).is_err() {
+                    return;
+                }
+            }
+
+            let mut source = String::new();
+            for face in &self.faces {
+                if let Some(face_path) = face.path.to_str() {
+                    source.push_str(&format!("{}\t{}\t{}\t{}\t{}\t{}\n",
+                                             face.modified,
+                                             face.names.family, face.names.style,
+                                             face.names.typographic_family, face.names.typographic_style,
+                                             face_path));
+                }
+            }
+            let _ = std::fs::write(&path, source);
+        }
+    }
+
+    // A family with whatever bold and italic faces it has. The old style names
+    // are tried first since they're exactly regular/bold/italic/bold italic.
+    pub fn find_family(&self, name: &str) -> Option<FontFamily> {
+        let name = normalized_name(name);
+
+        let by_family : Vec<(&SystemFontFace, String)> =
+            self.faces.iter()
+            .filter(|face| normalized_name(&face.names.family) == name)
+            .map(|face| (face, normalized_name(&face.names.style)))
+            .collect();
+        let faces =
+            if by_family.is_empty() {
+                self.faces.iter()
+                    .filter(|face| normalized_name(&face.names.typographic_family) == name)
+                    .map(|face| (face, normalized_name(&face.names.typographic_style)))
+                    .collect()
+            } else {
+                by_family
+            };
+
+        let face_with_style =
+            |styles: &[&str]| -> Option<String> {
+                faces.iter()
+                    .find(|(_, style)| styles.contains(&style.as_str()))
+                    .map(|(face, _)| face.path.to_string_lossy().into_owned())
+            };
+
+        let regular = face_with_style(&["regular", "book", "normal", "roman"])
+            .or_else(|| faces.first().map(|(face, _)| face.path.to_string_lossy().into_owned()))?;
+        Some(FontFamily {
+            regular,
+            bold: face_with_style(&["bold"]),
+            italic: face_with_style(&["italic", "oblique"]),
+            bold_italic: face_with_style(&["bolditalic", "boldoblique"]),
+        })
+    }
+
+    // One particular face, by "family style", like "DejaVu Sans Condensed Bold".
+    pub fn find_face(&self, name: &str) -> Option<&Path> {
+        let name = normalized_name(name);
+        self.faces.iter()
+            .find(
+                |face| {
+                    normalized_name(&format!("{}{}", face.names.family, face.names.style)) == name ||
+                    normalized_name(&format!("{}{}", face.names.typographic_family, face.names.typographic_style)) == name
+                })
+            .map(|face| face.path.as_path())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn font_face_names_from_name_table() {
+    let names = read_font_face_names(Path::new("data/fonts/libre-baskerville/LibreBaskerville-Bold.ttf")).unwrap();
+    assert_eq!("Libre Baskerville", names.family);
+    assert_eq!("Bold", names.style);
+}
+#[test]
+fn font_names_are_normalized() {
+    assert_eq!(normalized_name("DejaVu Sans"), normalized_name("dejavu-sans"));
+}
@@ -0,0 +1,218 @@
+/*
+    Turning a string into positioned glyphs, for scripts where that isn't one glyph per
+    character from left to right: Arabic and Hebrew read right to left and join letters
+    up, Devanagari reorders and stacks marks, and Latin has ligatures.
+
+    A line is first split into runs of one direction with the Unicode bidi algorithm
+    (unicode-bidi), the runs are put in the order they appear on screen, and then
+    each run is shaped on its own by rustybuzz. Glyphs come out left to right, ready
+    to draw.
+
+    Both ways of drawing text use this, the line textures and the glyph atlas.
+*/
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextDirection {
+    // whichever way the first letter with a direction goes.
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl TextDirection {
+    // Never Auto. Text with no letters at all (numbers, punctuation) goes left to right.
+    pub fn resolve(self, text: &str) -> TextDirection {
+        match self {
+            TextDirection::Auto => {
+                match unicode_bidi::get_base_direction(text) {
+                    unicode_bidi::Direction::Rtl => TextDirection::RightToLeft,
+                    _ => TextDirection::LeftToRight,
+                }
+            },
+            direction => direction,
+        }
+    }
+}
+
+// The pieces of a line in the order they're drawn, left to right, and whether
+// each one reads right to left.
+pub fn visual_runs(text: &str, direction: TextDirection) -> Vec<(Range<usize>, bool)> {
+    // nearly everything is plain ascii, which can't have anything to reorder.
+    if text.is_ascii() && direction != TextDirection::RightToLeft {
+        return vec![(0..text.len(), false)];
+    }
+
+    let paragraph_level = match direction {
+        TextDirection::Auto => None,
+        TextDirection::LeftToRight => Some(unicode_bidi::Level::ltr()),
+        TextDirection::RightToLeft => Some(unicode_bidi::Level::rtl()),
+    };
+    let bidi_info = unicode_bidi::ParagraphBidiInfo::new(text, paragraph_level);
+    let (levels, runs) = bidi_info.visual_runs(0..text.len());
+
+    runs.into_iter()
+        .map(|run| {
+            let right_to_left = levels[run.start].is_rtl();
+            (run, right_to_left)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShapedGlyph {
+    pub glyph_id: u16,
+    // in pixels, x from the start of the line, y up from the baseline.
+    pub x: f32,
+    pub y: f32,
+}
+
+// Keeps its buffers between lines so shaping doesn't allocate every time.
+pub struct TextShaper {
+    unicode_buffer: Option<rustybuzz::UnicodeBuffer>,
+    glyphs: Vec<ShapedGlyph>,
+}
+
+impl TextShaper {
+    pub fn new() -> TextShaper {
+        TextShaper {
+            unicode_buffer: None,
+            glyphs: Vec::new(),
+        }
+    }
+
+    // Returns the glyphs left to right and how wide the line is, in pixels.
+    pub fn shape(&mut self,
+                 face: &rustybuzz::Face,
+                 text: &str,
+                 font_size: u16,
+                 direction: TextDirection) -> (&[ShapedGlyph], f32) {
+        let scale = font_size as f32 / face.units_per_em() as f32;
+        self.glyphs.clear();
+
+        let mut pen_x = 0.0;
+        for (run, right_to_left) in visual_runs(text, direction) {
+            let mut unicode_buffer = self.unicode_buffer.take().unwrap_or_default();
+            unicode_buffer.push_str(&text[run]);
+            unicode_buffer.set_direction(if right_to_left { rustybuzz::Direction::RightToLeft } else { rustybuzz::Direction::LeftToRight });
+            unicode_buffer.guess_segment_properties();
+
+            // right to left runs come back already reversed, so this is still left to right.
+            let shaped = rustybuzz::shape(face, &[], unicode_buffer);
+            for (info, position) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()) {
+                self.glyphs.push(
+                    ShapedGlyph {
+                        glyph_id: info.glyph_id as u16,
+                        x: pen_x + position.x_offset as f32 * scale,
+                        y: position.y_offset as f32 * scale,
+                    });
+                pen_x += position.x_advance as f32 * scale;
+            }
+            self.unicode_buffer = Some(shaped.clear());
+        }
+
+        (&self.glyphs, pen_x)
+    }
+}
+
+// Draws shaped glyphs into white RGBA pixels (so color mod can tint them), width * height big.
+// SDL_ttf used to fake bold and italic for fonts without those faces, so that's done here too.
+pub fn rasterize_glyphs(font: &ab_glyph::FontRef,
+                        glyphs: &[ShapedGlyph],
+                        font_size: u16,
+                        ascent: f32,
+                        style: sdl2::ttf::FontStyle,
+                        width: u32,
+                        height: u32) -> Vec<u8> {
+    use ab_glyph::Font;
+    use sdl2::ttf::FontStyle;
+
+    let mut pixels = vec![255u8; (width * height * 4) as usize];
+    for alpha in pixels.iter_mut().skip(3).step_by(4) {
+        *alpha = 0;
+    }
+
+    let units_per_em = match font.units_per_em() {
+        Some(units_per_em) => units_per_em,
+        None => return pixels,
+    };
+    let scale = ab_glyph::PxScale::from(font_size as f32 * font.height_unscaled() / units_per_em);
+    let bold_offset = if style.contains(FontStyle::BOLD) { (font_size as f32 / 24.0).ceil().max(1.0) as i32 } else { 0 };
+    let italic_slant = if style.contains(FontStyle::ITALIC) { 0.2 } else { 0.0 };
+
+    let mut cover = |x: i32, y: i32, coverage: f32| {
+        if x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height {
+            let alpha = &mut pixels[((y as u32 * width + x as u32) * 4 + 3) as usize];
+            *alpha = (*alpha).max((coverage.min(1.0) * 255.0) as u8);
+        }
+    };
+
+    for glyph in glyphs {
+        let outline = match font.outline_glyph(
+            ab_glyph::GlyphId(glyph.glyph_id)
+                .with_scale_and_position(scale, ab_glyph::point(glyph.x, ascent - glyph.y))) {
+            Some(outline) => outline,
+            None => continue,
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(
+            |x, y, coverage| {
+                let y = bounds.min.y as i32 + y as i32;
+                let x = bounds.min.x as i32 + x as i32 + ((ascent - y as f32) * italic_slant).round() as i32;
+                for bold_x in 0..=bold_offset {
+                    cover(x + bold_x, y, coverage);
+                }
+            });
+    }
+
+    let mut fill_row = |y: f32, thickness: f32| {
+        for y in (y as i32)..((y + thickness.max(1.0)) as i32) {
+            for x in 0..width as i32 {
+                cover(x, y, 1.0);
+            }
+        }
+    };
+    if style.contains(FontStyle::UNDERLINE) {
+        fill_row(ascent + font_size as f32 / 10.0, font_size as f32 / 13.0);
+    }
+    if style.contains(FontStyle::STRIKETHROUGH) {
+        fill_row(ascent - font_size as f32 / 3.5, font_size as f32 / 13.0);
+    }
+
+    pixels
+}
+
+// How much wider than its advance a line gets when it's faked bold or italic.
+pub fn synthetic_style_overhang(font_size: u16, ascent: f32, style: sdl2::ttf::FontStyle) -> u32 {
+    use sdl2::ttf::FontStyle;
+    let mut overhang = 0.0;
+    if style.contains(FontStyle::BOLD) {
+        overhang += (font_size as f32 / 24.0).ceil().max(1.0);
+    }
+    if style.contains(FontStyle::ITALIC) {
+        overhang += (ascent * 0.2).ceil();
+    }
+    overhang as u32
+}
+
+#[cfg(test)]
+#[test]
+fn visual_runs_reorder_right_to_left_text() {
+    // "hello" in Hebrew, inside English.
+    let text = "say \u{05e9}\u{05dc}\u{05d5}\u{05dd} now";
+    let runs = visual_runs(text, TextDirection::Auto);
+    let run_texts : Vec<(&str, bool)> = runs.iter().map(|(run, right_to_left)| (&text[run.clone()], *right_to_left)).collect();
+    assert_eq!(vec![("say ", false), ("\u{05e9}\u{05dc}\u{05d5}\u{05dd}", true), (" now", false)], run_texts);
+
+    // in a right to left paragraph the English swaps ends, and the spaces go with the Hebrew.
+    let runs = visual_runs(text, TextDirection::RightToLeft);
+    let run_texts : Vec<&str> = runs.iter().map(|(run, _)| &text[run.clone()]).collect();
+    assert_eq!(vec!["now", " \u{05e9}\u{05dc}\u{05d5}\u{05dd} ", "say"], run_texts);
+}
+#[test]
+fn text_direction_resolves_from_first_strong_letter() {
+    assert_eq!(TextDirection::RightToLeft, TextDirection::Auto.resolve("\u{0645}\u{0631}\u{062d}\u{0628}\u{0627} hello"));
+    assert_eq!(TextDirection::LeftToRight, TextDirection::Auto.resolve("12 hello \u{0645}\u{0631}\u{062d}\u{0628}\u{0627}"));
+    assert_eq!(TextDirection::LeftToRight, TextDirection::Auto.resolve("1234"));
+    assert_eq!(TextDirection::RightToLeft, TextDirection::RightToLeft.resolve("hello"));
+}
@@ -0,0 +1,206 @@
+/*
+    The cache behind render_static_text: one texture per (font, text, size, style, direction).
+
+    It has a budget in bytes of texture memory, and throws out whatever was drawn
+    least recently to stay under it. Resizing the window makes every line come back
+    at a new size, which used to mean the old ones stayed around forever.
+
+    Lookups take borrowed strings. Entries are found by a hash of the key and then
+    compared field by field, so drawing a cached line doesn't allocate anything.
+*/
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+use crate::text_shaping::TextDirection;
+
+pub const DEFAULT_TEXT_CACHE_BUDGET_BYTES : usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TextTextureCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub textures: usize,
+    pub bytes_used: usize,
+    pub budget_bytes: usize,
+}
+
+// Borrowed, so looking something up doesn't need to allocate.
+#[derive(Clone, Copy, Hash, PartialEq)]
+pub struct TextTextureKey<'a> {
+    pub font_id: &'a str,
+    pub text: &'a str,
+    pub font_size: u16,
+    pub style: sdl2::ttf::FontStyle,
+    pub direction: TextDirection,
+}
+
+struct TextTextureCacheEntry {
+    font_id: String,
+    text: String,
+    font_size: u16,
+    style: sdl2::ttf::FontStyle,
+    direction: TextDirection,
+
+    texture: sdl2::render::Texture,
+    bytes: usize,
+    last_used: u64,
+}
+
+impl TextTextureCacheEntry {
+    fn matches(&self, key: &TextTextureKey) -> bool {
+        self.font_size == key.font_size && self.style == key.style && self.direction == key.direction &&
+            self.font_id == key.font_id && self.text == key.text
+    }
+}
+
+pub struct TextTextureCache {
+    // by key hash, the rare collisions just share a bucket.
+    entries: HashMap<u64, Vec<TextTextureCacheEntry>>,
+    // last use -> key hash, oldest first, so eviction doesn't have to search.
+    use_order: BTreeMap<u64, u64>,
+    clock: u64,
+
+    budget_bytes: usize,
+    stats: TextTextureCacheStats,
+}
+
+fn key_hash(key: &TextTextureKey) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl TextTextureCache {
+    pub fn new(budget_bytes: usize) -> TextTextureCache {
+        TextTextureCache {
+            entries: HashMap::new(),
+            use_order: BTreeMap::new(),
+            clock: 0,
+            budget_bytes,
+            stats: TextTextureCacheStats::default(),
+        }
+    }
+
+    // Counts as a use, and towards the hit/miss numbers.
+    pub fn get(&mut self, key: &TextTextureKey) -> Option<&mut sdl2::render::Texture> {
+        let hash = key_hash(key);
+        self.clock += 1;
+        let now = self.clock;
+
+        let entry = self.entries.get_mut(&hash)
+            .and_then(|bucket| bucket.iter_mut().find(|entry| entry.matches(key)));
+
+        match entry {
+            Some(entry) => {
+                self.stats.hits += 1;
+                self.use_order.remove(&entry.last_used);
+                self.use_order.insert(now, hash);
+                entry.last_used = now;
+                Some(&mut entry.texture)
+            },
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    // Just looking, doesn't count as a use.
+    pub fn peek_mut(&mut self, key: &TextTextureKey) -> Option<&mut sdl2::render::Texture> {
+        self.entries.get_mut(&key_hash(key))
+            .and_then(|bucket| bucket.iter_mut().find(|entry| entry.matches(key)))
+            .map(|entry| &mut entry.texture)
+    }
+
+    pub fn insert(&mut self, key: &TextTextureKey, texture: sdl2::render::Texture) {
+        let hash = key_hash(key);
+        self.clock += 1;
+        let now = self.clock;
+
+        let sdl2::render::TextureQuery { width, height, .. } = texture.query();
+        // text is always rendered blended, so 32 bits a pixel.
+        let bytes = width as usize * height as usize * 4;
+
+        self.remove_entry(hash, |entry| entry.matches(key));
+        self.entries.entry(hash).or_default().push(
+            TextTextureCacheEntry {
+                font_id: key.font_id.to_owned(),
+                text: key.text.to_owned(),
+                font_size: key.font_size,
+                style: key.style,
+                direction: key.direction,
+                texture,
+                bytes,
+                last_used: now,
+            });
+        self.use_order.insert(now, hash);
+        self.stats.bytes_used += bytes;
+        self.stats.textures += 1;
+
+        self.evict_over_budget();
+    }
+
+    // Oldest first. The newest texture always stays, if it's over budget all by
+    // itself it's still what's being drawn.
+    fn evict_over_budget(&mut self) {
+        while self.stats.bytes_used > self.budget_bytes && self.stats.textures > 1 {
+            let (last_used, hash) = match self.use_order.iter().next() {
+                Some((last_used, hash)) => (*last_used, *hash),
+                None => break,
+            };
+            self.remove_entry(hash, |entry| entry.last_used == last_used);
+            self.stats.evictions += 1;
+        }
+    }
+
+    fn remove_entry<F: Fn(&TextTextureCacheEntry) -> bool>(&mut self, hash: u64, predicate: F) {
+        if let Some(bucket) = self.entries.get_mut(&hash) {
+            if let Some(index) = bucket.iter().position(predicate) {
+                let entry = bucket.swap_remove(index);
+                self.use_order.remove(&entry.last_used);
+                self.stats.bytes_used -= entry.bytes;
+                self.stats.textures -= 1;
+                unsafe{ entry.texture.destroy(); }
+            }
+
+            if bucket.is_empty() {
+                self.entries.remove(&hash);
+            }
+        }
+    }
+
+    // Everything drawn in one font, for when the font file changes.
+    pub fn remove_font(&mut self, font_id: &str) {
+        let stale : Vec<(u64, u64)> =
+            self.entries.iter()
+            .flat_map(|(hash, bucket)| bucket.iter().map(move |entry| (*hash, entry)))
+            .filter(|(_, entry)| entry.font_id == font_id)
+            .map(|(hash, entry)| (hash, entry.last_used))
+            .collect();
+
+        for (hash, last_used) in stale {
+            self.remove_entry(hash, |entry| entry.last_used == last_used);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for (_, bucket) in self.entries.drain() {
+            for entry in bucket {
+                unsafe{ entry.texture.destroy(); }
+            }
+        }
+        self.use_order.clear();
+        self.stats.bytes_used = 0;
+        self.stats.textures = 0;
+    }
+
+    pub fn set_budget_bytes(&mut self, budget_bytes: usize) {
+        self.budget_bytes = budget_bytes;
+        self.evict_over_budget();
+    }
+
+    pub fn stats(&self) -> TextTextureCacheStats {
+        TextTextureCacheStats { budget_bytes: self.budget_bytes, .. self.stats }
+    }
+}
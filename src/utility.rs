@@ -60,6 +60,17 @@ pub fn remove_comments_from_source(source : &str) -> String {
     filtered
 }
 
+// remove_comments_from_source drops whole lines, so a line number in its output
+// needs this to point back at the right line of the file. Both count from 1.
+pub fn line_number_before_removing_comments(source: &str, line_number: usize) -> usize {
+    source.split("\n")
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('#'))
+        .nth(line_number.saturating_sub(1))
+        .map(|(index, _)| index + 1)
+        .unwrap_or(line_number)
+}
+
 pub fn load_file(file_name: &str) -> Result<String, &'static str> {
     use std::io::Read;
     use std::fs::File;
@@ -79,8 +90,78 @@ pub fn load_file(file_name: &str) -> Result<String, &'static str> {
     }
 }
 
-pub fn file_last_modified_time(file_name: &str) -> std::time::SystemTime {
-    let metadata = std::fs::metadata(file_name)
-        .expect("metadata retrieval failed... Deleted file?");
-    metadata.modified().expect("metadata modified time failed? No time?")
+// None if the file is gone, which happens for a moment whenever an editor saves
+// by renaming a new file over the old one.
+pub fn file_last_modified_time<P: AsRef<std::path::Path>>(file_name: P) -> Option<std::time::SystemTime> {
+    std::fs::metadata(file_name)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// hours, minutes, seconds of the wall clock. Local time where I know how
+// to get it, otherwise UTC which is better than nothing.
+pub fn wall_clock_time() -> (u32, u32, u32) {
+    #[cfg(unix)]
+    {
+        unsafe {
+            let now = libc::time(std::ptr::null_mut());
+            let mut local_time : libc::tm = std::mem::zeroed();
+            if !libc::localtime_r(&now, &mut local_time).is_null() {
+                return (local_time.tm_hour as u32,
+                        local_time.tm_min as u32,
+                        local_time.tm_sec as u32);
+            }
+        }
+    }
+
+    let seconds_since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let seconds_today = seconds_since_epoch % 86400;
+    ((seconds_today / 3600) as u32,
+     ((seconds_today / 60) % 60) as u32,
+     (seconds_today % 60) as u32)
+}
+
+pub fn format_elapsed_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds / 60) % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+// Where the user's settings live, this doesn't create it.
+pub fn user_config_directory() -> Option<std::path::PathBuf> {
+    use std::env;
+    use std::path::PathBuf;
+
+    #[cfg(target_os = "windows")]
+    let base_directory = env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(not(target_os = "windows"))]
+    let base_directory =
+        env::var_os("XDG_CONFIG_HOME")
+        .filter(|directory| !directory.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+
+    base_directory.map(|directory| directory.join("slideshow"))
+}
+
+#[cfg(test)]
+#[test]
+fn line_numbers_skip_comments() {
+    let source = "# title\n$page\n# note to self\nhello\n$end_page\n";
+    assert_eq!(2, line_number_before_removing_comments(source, 1));
+    assert_eq!(4, line_number_before_removing_comments(source, 2));
+    assert_eq!(5, line_number_before_removing_comments(source, 3));
+}
+#[test]
+fn elapsed_time_format() {
+    assert_eq!("00:00", format_elapsed_time(-1.0));
+    assert_eq!("01:05", format_elapsed_time(65.9));
+    assert_eq!("1:00:00", format_elapsed_time(3600.0));
 }